@@ -6,7 +6,18 @@
 
 const DEFAULT_CONFIG: &str = include_str!("dook.json");
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, strum::EnumIter)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Eq,
+    PartialEq,
+    Hash,
+    strum::EnumIter,
+    strum::EnumString,
+    strum::IntoStaticStr,
+)]
+#[strum(serialize_all = "lowercase")]
 pub enum LanguageName {
     Rust,
     Python,
@@ -16,6 +27,9 @@ pub enum LanguageName {
     C,
     CPlusPlus,
     Go,
+    CSharp,
+    Json,
+    Bash,
 }
 
 merde::derive! {
@@ -29,6 +43,9 @@ merde::derive! {
         "c" => C,
         "cplusplus" => CPlusPlus,
         "go" => Go,
+        "csharp" => CSharp,
+        "json" => Json,
+        "bash" => Bash,
     }
 }
 
@@ -43,6 +60,9 @@ impl LanguageName {
             LanguageName::C => tree_sitter_c::LANGUAGE.into(),
             LanguageName::CPlusPlus => tree_sitter_cpp::LANGUAGE.into(),
             LanguageName::Go => tree_sitter_go::LANGUAGE.into(),
+            LanguageName::CSharp => tree_sitter_c_sharp::LANGUAGE.into(),
+            LanguageName::Json => tree_sitter_json::LANGUAGE.into(),
+            LanguageName::Bash => tree_sitter_bash::LANGUAGE.into(),
         }
     }
 }
@@ -128,69 +148,304 @@ struct LanguageConfig {
     parent_exclusions: std::vec::Vec<String>,
     recurse_patterns: Option<std::vec::Vec<MultiLineString>>,
     comments: Option<Vec<String>>,
+    trim_trailing_blank_lines: Option<bool>,
 }
 
 merde::derive! {
-    impl (Deserialize) for struct LanguageConfig { match_patterns, sibling_patterns, parent_patterns, parent_exclusions, recurse_patterns, comments }
+    impl (Deserialize) for struct LanguageConfig { match_patterns, sibling_patterns, parent_patterns, parent_exclusions, recurse_patterns, comments, trim_trailing_blank_lines }
+}
+
+impl LanguageConfig {
+    /// Pretty-printed JSON, laid out like the built-in config.
+    fn to_json(&self) -> String {
+        fn quote(s: &str) -> String {
+            merde::json::to_string(&String::from(s)).unwrap()
+        }
+        fn string_list<'a>(items: impl IntoIterator<Item = &'a String>, indent: &str) -> String {
+            let items: std::vec::Vec<String> = items
+                .into_iter()
+                .map(|item| format!("{}  {}", indent, quote(item)))
+                .collect();
+            match items.is_empty() {
+                true => String::from("[]"),
+                false => format!("[\n{}\n{}]", items.join(",\n"), indent),
+            }
+        }
+        fn query_list(queries: &[MultiLineString]) -> String {
+            let items: std::vec::Vec<String> = queries
+                .iter()
+                .map(|query| match query {
+                    MultiLineString::One(line) => format!("      {}", quote(line)),
+                    MultiLineString::Many(lines) => {
+                        format!("      {}", string_list(lines, "      "))
+                    }
+                })
+                .collect();
+            match items.is_empty() {
+                true => String::from("[]"),
+                false => format!("[\n{}\n    ]", items.join(",\n")),
+            }
+        }
+        let mut fields = vec![
+            ("match_patterns", query_list(&self.match_patterns)),
+            (
+                "sibling_patterns",
+                string_list(&self.sibling_patterns, "    "),
+            ),
+            (
+                "parent_patterns",
+                string_list(&self.parent_patterns, "    "),
+            ),
+            (
+                "parent_exclusions",
+                string_list(&self.parent_exclusions, "    "),
+            ),
+        ];
+        if let Some(recurse_patterns) = &self.recurse_patterns {
+            fields.push(("recurse_patterns", query_list(recurse_patterns)));
+        }
+        if let Some(comments) = &self.comments {
+            fields.push(("comments", string_list(comments, "    ")));
+        }
+        if let Some(trim) = self.trim_trailing_blank_lines {
+            fields.push(("trim_trailing_blank_lines", trim.to_string()));
+        }
+        let fields: std::vec::Vec<String> = fields
+            .into_iter()
+            .map(|(key, value)| format!("    {}: {}", quote(key), value))
+            .collect();
+        format!("{{\n{}\n  }}", fields.join(",\n"))
+    }
 }
 
 #[derive(Debug, PartialEq)]
-pub struct Config(std::collections::HashMap<LanguageName, LanguageConfig>);
+struct ConfigFile(std::collections::HashMap<LanguageName, LanguageConfig>);
 
 merde::derive! {
-    impl (Deserialize) for struct Config transparent
+    impl (Deserialize) for struct ConfigFile transparent
+}
+
+/// Where a config came from, for error messages.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigSource {
+    BuiltIn,
+    File(std::path::PathBuf),
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::BuiltIn => write!(f, "built-in config"),
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// What's wrong with a language's queries, before anyone says which config they're from.
+#[derive(Debug)]
+pub enum QueryProblem {
+    Invalid(tree_sitter::QueryError),
+    /// with what the query does capture, and which of those was likely meant instead
+    MissingCapture {
+        capture: String,
+        present: std::vec::Vec<String>,
+        meant: Option<String>,
+    },
+}
+
+impl From<tree_sitter::QueryError> for QueryProblem {
+    fn from(e: tree_sitter::QueryError) -> Self {
+        QueryProblem::Invalid(e)
+    }
+}
+
+/// Why a language's patterns from some config can't be used.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// tree-sitter couldn't compile a query, or doesn't know a node type or field name.
+    Query {
+        language_name: LanguageName,
+        source: ConfigSource,
+        error: tree_sitter::QueryError,
+    },
+    /// A query compiles but doesn't capture something we read from it, like @name.
+    MissingCapture {
+        language_name: LanguageName,
+        source: ConfigSource,
+        capture: String,
+        present: std::vec::Vec<String>,
+        meant: Option<String>,
+    },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Query {
+                language_name,
+                source,
+                error,
+            } => write!(
+                f,
+                "Error in {:?} patterns from {}: {}",
+                language_name, source, error
+            ),
+            ConfigError::MissingCapture {
+                language_name,
+                source,
+                capture,
+                present,
+                meant,
+            } => {
+                write!(
+                    f,
+                    "Error in {:?} patterns from {}: @{} is missing",
+                    language_name, source, capture
+                )?;
+                match present.is_empty() {
+                    true => write!(f, ", and the query captures nothing")?,
+                    false => write!(
+                        f,
+                        "; the query captures {}",
+                        present
+                            .iter()
+                            .map(|name| format!("@{}", name))
+                            .collect::<std::vec::Vec<_>>()
+                            .join(", ")
+                    )?,
+                }
+                match meant {
+                    Some(meant) => write!(f, " (did you mean @{} instead of @{}?)", capture, meant),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    language_configs: std::collections::HashMap<LanguageName, LanguageConfig>,
+    pub source: ConfigSource,
 }
 
 impl Config {
-    pub fn load(explicit_path: Option<std::ffi::OsString>) -> std::io::Result<Option<Self>> {
-        use merde::IntoStatic;
-        let file_contents = match explicit_path {
-            // explicitly requested file paths expose any errors reading
-            Some(p) => std::fs::read(std::path::PathBuf::from(p))?,
-            // the default file path is more forgiving...
-            None => match directories::ProjectDirs::from("com", "melonisland", "dook") {
-                // if we have no idea how to find it, just give up
-                None => return Ok(None),
-                Some(d) => {
-                    let default_path = d.config_dir().join("dook.json");
-                    match std::fs::read(&default_path) {
-                        // unwrap the contents if we successfully read it
-                        Ok(contents) => contents,
-                        Err(e) => match e.kind() {
-                            // silently eat NotFound
-                            std::io::ErrorKind::NotFound => return Ok(None),
-                            // log other errors but don't let them stop us from trying to work in a degraded environment
-                            _ => {
-                                log::warn!("Error reading config at {:?}, falling back to built-in default: {:?}", default_path, e);
-                                return Ok(None);
-                            }
-                        },
+    /// Load every custom config that applies, in order of precedence:
+    /// the explicitly requested one if any,
+    /// then any project-local `.dook/dook.json` from here up to the root, nearest first,
+    /// then, unless one was explicitly requested, the one in the user's config directory
+    /// (`DOOK_CONFIG_DIR` if set).
+    pub fn load(explicit_path: Option<std::ffi::OsString>) -> std::io::Result<std::vec::Vec<Self>> {
+        let mut configs = std::vec::Vec::new();
+        // explicitly requested file paths expose any errors reading
+        if let Some(p) = &explicit_path {
+            configs.push(Self::load_from_file(std::path::Path::new(p))?);
+        }
+        // so do project-local configs, since someone must have gone to the trouble of making them
+        if let Ok(cwd) = std::env::current_dir() {
+            for path in find_project_configs(&cwd) {
+                configs.push(Self::load_from_file(&path)?);
+            }
+        }
+        if explicit_path.is_none() {
+            if let Some(c) = Self::load_user_default()? {
+                configs.push(c);
+            }
+        }
+        Ok(configs)
+    }
+
+    fn load_user_default() -> std::io::Result<Option<Self>> {
+        // the default file path is more forgiving...
+        let config_dir = match std::env::var_os("DOOK_CONFIG_DIR") {
+            Some(dir) => Some(std::path::PathBuf::from(dir)),
+            None => directories::ProjectDirs::from("com", "melonisland", "dook")
+                .map(|d| d.config_dir().to_path_buf()),
+        };
+        match config_dir {
+            // if we have no idea how to find it, just give up
+            None => Ok(None),
+            Some(d) => {
+                let default_path = d.join("dook.json");
+                match std::fs::read(&default_path) {
+                    // parse the contents if we successfully read it
+                    Ok(contents) => {
+                        Self::parse(contents, ConfigSource::File(default_path)).map(Some)
                     }
+                    Err(e) => match e.kind() {
+                        // silently eat NotFound
+                        std::io::ErrorKind::NotFound => Ok(None),
+                        // log other errors but don't let them stop us from trying to work in a degraded environment
+                        _ => {
+                            log::warn!("Error reading config at {:?}, falling back to built-in default: {:?}", default_path, e);
+                            Ok(None)
+                        }
+                    },
                 }
-            },
-        }.to_ascii_lowercase();
-        let contents_lowercase = std::str::from_utf8(&file_contents)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        let deserialize_result: Result<Config, _> = merde::json::from_str(contents_lowercase);
+            }
+        }
+    }
+
+    fn load_from_file(path: &std::path::Path) -> std::io::Result<Self> {
+        let contents = std::fs::read(path).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("Error reading {}: {}", path.display(), e))
+        })?;
+        Self::parse(contents, ConfigSource::File(path.to_path_buf()))
+    }
+
+    fn parse(file_contents: std::vec::Vec<u8>, source: ConfigSource) -> std::io::Result<Self> {
+        use merde::IntoStatic;
+        let file_contents = file_contents.to_ascii_lowercase();
+        let contents_lowercase = std::str::from_utf8(&file_contents).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Error in {}: {}", source, e),
+            )
+        })?;
+        let deserialize_result: Result<ConfigFile, _> = merde::json::from_str(contents_lowercase);
         match deserialize_result {
-            Ok(c) => Ok(Some(c.into_static())),
+            Ok(c) => Ok(Self {
+                language_configs: c.into_static().0,
+                source,
+            }),
             Err(e) => Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
-                e.into_static(),
+                format!("Error in {}: {}", source, e),
             )),
         }
     }
 
     pub fn load_default() -> Self {
-        merde::json::from_str(&DEFAULT_CONFIG.to_ascii_lowercase()).unwrap()
+        let ConfigFile(language_configs) =
+            merde::json::from_str(&DEFAULT_CONFIG.to_ascii_lowercase()).unwrap();
+        Self {
+            language_configs,
+            source: ConfigSource::BuiltIn,
+        }
+    }
+
+    pub fn has_language(&self, language_name: LanguageName) -> bool {
+        self.language_configs.contains_key(&language_name)
+    }
+
+    /// This config's entry for `language_name`, as the text of a config file containing only it.
+    pub fn language_config_json(&self, language_name: LanguageName) -> Option<String> {
+        let language_config = self.language_configs.get(&language_name)?;
+        let key: &str = language_name.into();
+        Some(format!(
+            "{{\n  \"{}\": {}\n}}",
+            key,
+            language_config.to_json()
+        ))
     }
 
     pub fn get_language_info(
         &self,
         language_name: LanguageName,
-    ) -> Option<Result<LanguageInfo, tree_sitter::QueryError>> {
-        let Self(config_map) = self;
-        let language_config = config_map.get(&language_name)?;
+    ) -> Option<Result<LanguageInfo, ConfigError>> {
+        let language_config = self.language_configs.get(&language_name)?;
         let language = language_name.get_language();
         let match_patterns: std::vec::Vec<String> = language_config
             .match_patterns
@@ -202,23 +457,63 @@ impl Config {
             .as_ref()
             .map(|v| v.iter().map(String::from).collect())
             .unwrap_or_default();
-        Some(LanguageInfo::new(
-            &language,
-            match_patterns,
-            &language_config.sibling_patterns,
-            &language_config.parent_patterns,
-            &language_config.parent_exclusions,
-            recurse_patterns,
-        ))
+        Some(
+            LanguageInfo::new(
+                &language,
+                match_patterns,
+                &language_config.sibling_patterns,
+                &language_config.parent_patterns,
+                &language_config.parent_exclusions,
+                recurse_patterns,
+            )
+            .map(|info| LanguageInfo {
+                trim_trailing_blank_lines: language_config
+                    .trim_trailing_blank_lines
+                    .unwrap_or(true),
+                ..info
+            })
+            .map_err(|problem| match problem {
+                QueryProblem::Invalid(error) => ConfigError::Query {
+                    language_name,
+                    source: self.source.clone(),
+                    error,
+                },
+                QueryProblem::MissingCapture {
+                    capture,
+                    present,
+                    meant,
+                } => ConfigError::MissingCapture {
+                    language_name,
+                    source: self.source.clone(),
+                    capture,
+                    present,
+                    meant,
+                },
+            }),
+        )
     }
 }
 
+/// Paths to `.dook/dook.json` in `start` and each of its ancestors, nearest first,
+/// the way git looks for `.gitignore`.
+fn find_project_configs(start: &std::path::Path) -> std::vec::Vec<std::path::PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(".dook").join("dook.json"))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
 pub struct LanguageInfo {
     pub match_patterns: std::vec::Vec<tree_sitter::Query>,
     pub sibling_patterns: std::vec::Vec<std::num::NonZero<u16>>,
     pub parent_patterns: std::vec::Vec<std::num::NonZero<u16>>,
     pub parent_exclusions: std::vec::Vec<std::num::NonZero<u16>>,
     pub recurse_patterns: std::vec::Vec<tree_sitter::Query>,
+    /// Leave blank lines at the end of a definition out of its range.
+    pub trim_trailing_blank_lines: bool,
+    /// Problems worth mentioning that don't stop the queries from working, like unused captures.
+    pub warnings: std::vec::Vec<String>,
 }
 
 impl LanguageInfo {
@@ -240,14 +535,22 @@ impl LanguageInfo {
         parent_patterns: I3,
         parent_exclusions: I4,
         recurse_patterns: I5,
-    ) -> Result<Self, tree_sitter::QueryError> {
+    ) -> Result<Self, QueryProblem> {
         fn compile_queries<Item: AsRef<str>, II: IntoIterator<Item = Item>>(
             language: &tree_sitter::Language,
             sources: II,
-        ) -> Result<std::vec::Vec<tree_sitter::Query>, tree_sitter::QueryError> {
+            required_captures: &[&str],
+            used_captures: &[&str],
+            warnings: &mut std::vec::Vec<String>,
+        ) -> Result<std::vec::Vec<tree_sitter::Query>, QueryProblem> {
             sources
                 .into_iter()
-                .map(|source| tree_sitter::Query::new(language, source.as_ref()))
+                .map(|source| {
+                    let query = tree_sitter::Query::new(language, source.as_ref())?;
+                    check_captures(&query, required_captures)?;
+                    warnings.extend(unused_captures(&query, source.as_ref(), used_captures));
+                    Ok(query)
+                })
                 .collect()
         }
         fn resolve_node_types<Item: AsRef<str>, II: IntoIterator<Item = Item>>(
@@ -291,26 +594,200 @@ impl LanguageInfo {
                 })
                 .collect()
         }
+        let mut warnings = std::vec::Vec::new();
         Ok(Self {
-            match_patterns: compile_queries(language, match_patterns)?,
+            match_patterns: compile_queries(
+                language,
+                match_patterns,
+                &["name", "def"],
+                &["name", "def"],
+                &mut warnings,
+            )?,
             sibling_patterns: resolve_node_types(language, sibling_patterns)?,
             parent_patterns: resolve_node_types(language, parent_patterns)?,
             parent_exclusions: resolve_field_names(language, parent_exclusions)?,
-            recurse_patterns: compile_queries(language, recurse_patterns)?,
+            recurse_patterns: compile_queries(
+                language,
+                recurse_patterns,
+                &["name"],
+                &["name", "qualifier"],
+                &mut warnings,
+            )?,
+            trim_trailing_blank_lines: true,
+            warnings,
         })
     }
 }
 
+/// Make sure `query` has each of `required` captures, and if not, say which ones it does have.
+fn check_captures(query: &tree_sitter::Query, required: &[&str]) -> Result<(), QueryProblem> {
+    let present = query.capture_names();
+    let Some(missing) = required.iter().find(|name| !present.contains(name)) else {
+        return Ok(());
+    };
+    // such as @definition for @def, or a typo
+    let meant = present
+        .iter()
+        .filter(|name| !required.contains(name))
+        .filter(|name| name.starts_with(missing) || edit_distance(name, missing) <= 2)
+        .min_by_key(|name| edit_distance(name, missing));
+    Err(QueryProblem::MissingCapture {
+        capture: String::from(*missing),
+        present: present.iter().map(|name| String::from(*name)).collect(),
+        meant: meant.map(|name| String::from(*name)),
+    })
+}
+
+/// Describe each capture in `query` that's neither in `used` nor mentioned again in its `source`,
+/// as it would be by a predicate like `#eq?`, since nothing will ever look at it.
+fn unused_captures(
+    query: &tree_sitter::Query,
+    source: &str,
+    used: &[&str],
+) -> std::vec::Vec<String> {
+    query
+        .capture_names()
+        .iter()
+        .filter(|name| !used.contains(name))
+        .filter(|name| {
+            let mention = format!("@{}", name);
+            source
+                .match_indices(&mention)
+                .filter(|(i, _)| {
+                    !source[i + mention.len()..]
+                        .starts_with(|c: char| c.is_alphanumeric() || "_-.".contains(c))
+                })
+                .count()
+                < 2
+        })
+        .map(|name| format!("@{} is captured but never used, in query: {}", name, source))
+        .collect()
+}
+
+/// How many characters to insert, delete, or replace to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: std::vec::Vec<char> = b.chars().collect();
+    let mut row: std::vec::Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(a_char != *b_char));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn missing_captures() {
+        let lint = |query: &str| {
+            let json = format!(
+                r#"{{"python": {{"match_patterns": [{:?}], "sibling_patterns": [], "parent_patterns": [], "parent_exclusions": []}}}}"#,
+                query
+            );
+            Config::parse(json.into_bytes(), ConfigSource::File("x.json".into()))
+                .unwrap()
+                .get_language_info(LanguageName::Python)
+                .unwrap()
+                .err()
+                .map(|e| e.to_string())
+        };
+        assert_eq!(
+            lint("(function_definition name: (identifier) @name) @def"),
+            None
+        );
+        assert_eq!(
+            lint("(function_definition name: (identifier) @name) @definition").as_deref(),
+            Some("Error in Python patterns from x.json: @def is missing; the query captures @name, @definition (did you mean @def instead of @definition?)")
+        );
+        assert_eq!(
+            lint("(function_definition name: (identifier) @nmae) @def").as_deref(),
+            Some("Error in Python patterns from x.json: @name is missing; the query captures @nmae, @def (did you mean @name instead of @nmae?)")
+        );
+        assert_eq!(
+            lint("(function_definition name: (identifier) @name) @body").as_deref(),
+            Some("Error in Python patterns from x.json: @def is missing; the query captures @name, @body")
+        );
+        assert_eq!(
+            lint("(identifier)").as_deref(),
+            Some("Error in Python patterns from x.json: @name is missing, and the query captures nothing")
+        );
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn unused_captures_warn() {
+        let language = LanguageName::Python.get_language();
+        let warnings = |query: &str| {
+            LanguageInfo::new(&language, [query], [""; 0], [""; 0], [""; 0], [""; 0])
+                .unwrap()
+                .warnings
+        };
+        assert!(warnings("(function_definition name: (identifier) @name) @def").is_empty());
+        // used by a predicate, even with a longer capture name alongside it
+        assert!(warnings(
+            "(function_definition name: (identifier) @name @f (#eq? @f \"main\") body: (_) @fo) @def"
+        )
+        .iter()
+        .map(|w| &w[..4])
+        .eq(["@fo "]));
+        assert_eq!(
+            warnings("(function_definition name: (identifier) @name body: (_) @body) @def").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn project_configs_nearest_first() {
+        let root = std::env::temp_dir().join(format!("dook-test-{}", std::process::id()));
+        let inner = root.join("a").join("b");
+        std::fs::create_dir_all(inner.join(".dook")).unwrap();
+        std::fs::create_dir_all(root.join(".dook")).unwrap();
+        std::fs::write(inner.join(".dook").join("dook.json"), "{}").unwrap();
+        std::fs::write(root.join(".dook").join("dook.json"), "{}").unwrap();
+        let found = find_project_configs(&inner);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(
+            found[..2],
+            [
+                inner.join(".dook").join("dook.json"),
+                root.join(".dook").join("dook.json"),
+            ]
+        );
+    }
+
+    #[test]
+    fn printed_config_round_trips() {
+        use strum::IntoEnumIterator;
+        let default_config = Config::load_default();
+        for language_name in LanguageName::iter() {
+            let json = default_config.language_config_json(language_name).unwrap();
+            let reparsed = Config::parse(json.into_bytes(), ConfigSource::BuiltIn).unwrap();
+            assert_eq!(
+                reparsed.language_configs.get(&language_name),
+                default_config.language_configs.get(&language_name),
+            );
+        }
+    }
+
     #[test]
     fn default_patterns_are_loadable() {
         use strum::IntoEnumIterator;
         let default_config = Config::load_default();
         for language_name in LanguageName::iter() {
-            default_config.get_language_info(language_name);
+            let language_info = default_config
+                .get_language_info(language_name)
+                .unwrap()
+                .unwrap();
+            assert_eq!(language_info.warnings, std::vec::Vec::<String>::new());
         }
     }
 }
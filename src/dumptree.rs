@@ -1,9 +1,12 @@
-/// dump the structure of a `tree_sitter::Tree` to standard output.
+/// dump the structure of a `tree_sitter::Tree` to `writer`.
+/// with `show_positions`, leaf nodes also say where they are, 1-based like editors count.
 pub fn dump_tree<I: AsRef<[u8]>, T: tree_sitter::TextProvider<I>>(
+    writer: &mut impl std::io::Write,
     tree: &tree_sitter::Tree,
     mut text_provider: T,
     use_color: bool,
-) {
+    show_positions: bool,
+) -> std::io::Result<()> {
     let mut depth: usize = 0;
     let mut sibling_idx = std::vec::Vec::<usize>::new();
     let mut cursor = tree.walk();
@@ -16,35 +19,48 @@ pub fn dump_tree<I: AsRef<[u8]>, T: tree_sitter::TextProvider<I>>(
     'treewalk: loop {
         let node = cursor.node();
         // indent
-        print!("{}", String::from(" ").repeat(depth));
+        write!(writer, "{}", String::from(" ").repeat(depth))?;
         // parent's field name if it's there
         if let Some(parent) = node.parent() {
             if let Some(field_name) = parent
                 .field_name_for_child(*sibling_idx.last().unwrap() as u32 /* mod 2**32 */)
             {
-                print!(
+                write!(
+                    writer,
                     "{}{}{}:{} ",
                     color_fieldname, field_name, color_eq, color_end
-                );
+                )?;
             }
         }
         if node.child_count() > 0 {
-            println!(
+            writeln!(
+                writer,
                 "{}({}{}{}",
                 color_paren,
                 color_nodekind,
                 node.kind(),
                 color_end
-            );
+            )?;
         } else {
             let node_content = text_provider
                 .text(node)
                 .map(|t| String::from(std::str::from_utf8(t.as_ref()).unwrap()))
                 .collect::<Vec<_>>()
                 .concat();
+            let position = match show_positions {
+                true => format!(
+                    " [{}:{}-{}:{}]",
+                    node.start_position().row + 1,
+                    node.start_position().column + 1,
+                    node.end_position().row + 1,
+                    node.end_position().column + 1,
+                ),
+                false => String::new(),
+            };
             if node.is_named() {
-                println!(
-                    "{}({}{}{} = {}{:?}{}){}",
+                writeln!(
+                    writer,
+                    "{}({}{}{} = {}{:?}{}){}{}",
                     color_paren,
                     color_nodekind,
                     node.kind(),
@@ -52,21 +68,27 @@ pub fn dump_tree<I: AsRef<[u8]>, T: tree_sitter::TextProvider<I>>(
                     color_literal,
                     node_content,
                     color_paren,
-                    color_end
-                );
+                    color_end,
+                    position
+                )?;
             } else {
-                println!("{}{:?}{}", color_literal, node_content, color_end);
+                writeln!(
+                    writer,
+                    "{}{:?}{}{}",
+                    color_literal, node_content, color_end, position
+                )?;
             }
         }
         // depth first traversal
         if !cursor.goto_first_child() {
             while !cursor.goto_next_sibling() {
-                println!(
+                writeln!(
+                    writer,
                     "{}{}){}",
                     String::from(" ").repeat(depth),
                     color_paren,
                     color_end
-                );
+                )?;
                 if !cursor.goto_parent() {
                     break 'treewalk;
                 } else {
@@ -82,4 +104,5 @@ pub fn dump_tree<I: AsRef<[u8]>, T: tree_sitter::TextProvider<I>>(
             sibling_idx.push(0)
         }
     }
+    Ok(())
 }
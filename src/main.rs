@@ -7,9 +7,109 @@
 
 mod config;
 mod dumptree;
+mod outputs;
 mod paging;
 mod range_union;
 mod searches;
+mod walk;
+
+/// The ripgrep executable to run, which `DOOK_RIPGREP` can override.
+fn ripgrep_program() -> std::ffi::OsString {
+    std::env::var_os("DOOK_RIPGREP").unwrap_or_else(|| "rg".into())
+}
+
+/// Whether `ripgrep_program()` runs, checked once per process (and warned about once if not).
+fn has_ripgrep() -> bool {
+    static HAS_RIPGREP: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *HAS_RIPGREP.get_or_init(|| {
+        match std::process::Command::new(ripgrep_program())
+            .arg("-V")
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+        {
+            Ok(status) => status.success(),
+            Err(e) => {
+                log::warn!(
+                    "ripgrep not found, falling back to a slower directory walk \
+                    (install ripgrep or set DOOK_RIPGREP to speed this up): {}",
+                    e
+                );
+                false
+            }
+        }
+    })
+}
+
+/// Sorted paths of files containing `pattern`, or of all files if it's None, that ripgrep
+/// (or failing that, a directory walk) doesn't ignore. Files that look binary are left out unless
+/// `text` is set. When nothing turns up or ripgrep fails, returns the exit code to pass along.
+fn candidate_files(
+    pattern: Option<&regex::Regex>,
+    text: bool,
+) -> std::io::Result<Result<std::vec::Vec<std::ffi::OsString>, u8>> {
+    use os_str_bytes::OsStrBytes;
+    if !has_ripgrep() {
+        let match_everything = regex::Regex::new("").unwrap();
+        let walk_start = std::time::Instant::now();
+        let filenames = walk::files_matching(
+            std::path::Path::new("./"),
+            pattern.unwrap_or(&match_everything),
+            text,
+        )?;
+        // each recursion pass walks again, so show what that costs
+        log::info!(
+            "directory walk found {} files in {:?}",
+            filenames.len(),
+            walk_start.elapsed()
+        );
+        return Ok(match filenames.is_empty() {
+            true => Err(1), // same as ripgrep's exit code when nothing matches
+            false => Ok(filenames),
+        });
+    }
+    let mut rg = std::process::Command::new(ripgrep_program());
+    if text {
+        rg.arg("--text");
+    }
+    match pattern {
+        Some(pattern) => rg.arg("-l").arg(pattern.as_str()),
+        None => rg.arg("--files"),
+    };
+    let rg_output = rg
+        .arg("-0")
+        .arg("./")
+        .stderr(std::process::Stdio::inherit())
+        .output()?;
+    if !rg_output.status.success() {
+        if let Some(e) = rg_output.status.code() {
+            return Ok(Err(e as u8)); // truncate to 8 bits
+        }
+        return Err(std::io::Error::other(format!("{}", rg_output.status)));
+    }
+    // TODO is this even actually the right way to convert stdout to OsStr?
+    let filenames: std::io::Result<std::vec::Vec<std::ffi::OsString>> = rg_output
+        .stdout
+        .split(|x| *x == 0)
+        .map(|x| match std::ffi::OsStr::from_io_bytes(x) {
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{:?}", std::vec::Vec::from(x)),
+            )),
+            Some(y) => Ok(y.to_os_string()),
+        })
+        .filter(|f| match f {
+            Ok(f) => !f.is_empty(),
+            _ => true,
+        })
+        .collect();
+    let mut filenames = filenames?;
+    filenames.sort_unstable();
+    Ok(Ok(filenames))
+}
+
+/// How many parsed files to keep between recursion passes.
+const PARSED_FILE_CACHE_SIZE: usize = 256;
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
 enum EnablementLevel {
@@ -19,23 +119,88 @@ enum EnablementLevel {
     Always,
 }
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// Excerpts of each definition, with context.
+    #[default]
+    Pretty,
+    /// `path:line:column:text` for each definition, like `rg --vimgrep`.
+    Vimgrep,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum GroupBy {
+    /// Each file's definitions together.
+    #[default]
+    File,
+    /// Each name's definitions together, across files; implies --label.
+    Name,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+enum SortOrder {
+    /// In the order files were searched, which can jump around when recursing.
+    #[default]
+    Scan,
+    /// By file path, keeping results from the same file in the order they were found.
+    Path,
+}
+
 #[derive(clap::Parser, Debug)]
+#[command(version, long_version = LONG_VERSION.as_str())]
 /// dook: Definition lookup in your code.
 struct Cli {
-    /// Regex to match against symbol names. Required unless using --dump.
-    pattern: Option<regex::Regex>,
+    /// Regex to match against whole symbol names (unless using --partial).
+    /// Required unless using --dump, --print-config, --stats, or --list-languages.
+    pattern: Option<String>,
+
+    /// Files to search, in order, instead of asking ripgrep which files mention the pattern.
+    paths: Vec<std::ffi::OsString>,
 
     /// Config file path
     #[arg(short, long, required = false)]
     config: Option<std::ffi::OsString>,
 
+    /// Use only the built-in config, ignoring project, user, and --config files, to tell whether those are the problem.
+    #[arg(long, conflicts_with = "config")]
+    no_config: bool,
+
     #[arg(long, value_enum, default_value_t)]
     color: EnablementLevel,
 
     #[arg(long, value_enum, default_value_t)]
     paging: EnablementLevel,
 
-    /// Apply no styling; specify twice to also disable paging.
+    /// Render output this many columns wide, even when not writing to a terminal.
+    #[arg(long, value_name = "N")]
+    width: Option<u16>,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    format: OutputFormat,
+
+    /// Order to print results in.
+    #[arg(long, value_enum, default_value_t)]
+    sort: SortOrder,
+
+    /// Wrap long lines.
+    #[arg(long, value_enum, default_value_t)]
+    wrap: outputs::WrapMode,
+
+    /// Show up to this many lines between excerpts instead of snipping them out.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    snip_gap: usize,
+
+    /// Rule to draw around file names when bat isn't installed.
+    #[arg(long, value_name = "STR", default_value = "=")]
+    file_rule: String,
+
+    /// Rule to draw between excerpts when bat isn't installed.
+    #[arg(long, value_name = "STR", default_value = "-")]
+    snip_rule: String,
+
+    /// Apply no styling; specify twice to also disable paging,
+    /// or thrice to print only the lines themselves (without file names or separators).
     #[arg(short, long, action = clap::ArgAction::Count)]
     plain: u8,
 
@@ -47,20 +212,216 @@ struct Cli {
     #[arg(long, overrides_with = "recurse")]
     _no_recurse: bool,
 
-    /// Dump the syntax tree of the specified file, for debugging extraction queries.
+    /// Show the whole enclosing class, function, etc, instead of just its header.
+    #[arg(long)]
+    full_scope: bool,
+
+    /// Search files that look binary too, in case they're mostly text. May be slow on real binaries.
+    #[arg(short = 'a', long)]
+    text: bool,
+
+    /// Print each definition separately, labeled with the name it defines.
+    #[arg(long)]
+    label: bool,
+
+    /// What to keep together in the output.
+    #[arg(long, value_enum, default_value_t)]
+    group_by: GroupBy,
+
+    /// Skip files that take longer than this many milliseconds to parse.
+    #[arg(long, value_name = "MS")]
+    parse_timeout_ms: Option<u64>,
+
+    /// Match the pattern anywhere in symbol names, so `foo` also finds `foobar`.
+    #[arg(long)]
+    partial: bool,
+
+    /// Treat the pattern as a literal name rather than a regex, so `Vec<T>` or `operator[]` need no escaping.
+    #[arg(short = 'F', long)]
+    fixed_strings: bool,
+
+    /// Show definitions whose names don't match the pattern. Scans every file in a supported language.
+    #[arg(long, conflicts_with = "recurse")]
+    invert_match: bool,
+
+    /// Show only definitions whose attached comments match this regex, such as `dook '.*' --doc-match retries`.
+    #[arg(long, value_name = "REGEX", conflicts_with = "only_names")]
+    doc_match: Option<regex::Regex>,
+
+    /// Note on stderr which language each searched file was parsed as, like `path: python`.
+    #[arg(long)]
+    show_language: bool,
+
+    /// Show only the first definition of each name, in the order files are searched.
+    #[arg(long)]
+    first: bool,
+
+    /// Print only the names of matching definitions, sorted and deduplicated (probably for shell completions).
+    #[arg(long)]
+    only_names: bool,
+
+    /// With --only-names, follow each name with a tab and `path:line` of its first definition.
+    #[arg(long, requires = "only_names")]
+    with_location: bool,
+
+    /// With --only-names, list every definition of each name rather than just the first,
+    /// each followed by its location like --with-location.
+    #[arg(long, requires = "only_names")]
+    all_matches: bool,
+
+    /// Print how many definitions matched in each file, and in total, instead of the definitions.
+    #[arg(long, conflicts_with = "only_names")]
+    count: bool,
+
+    /// Dump the syntax tree of the specified file, or of each file in the specified directory,
+    /// for debugging extraction queries.
     #[arg(long, required = false)]
     dump: Option<std::ffi::OsString>,
+
+    /// With --dump, print what this tree-sitter query captures instead of the whole tree.
+    #[arg(long, value_name = "QUERY", requires = "dump")]
+    dump_query: Option<String>,
+
+    /// With --dump, follow each leaf node with where it is, as `[line:column-line:column]`.
+    #[arg(long, requires = "dump")]
+    dump_positions: bool,
+
+    /// Print the config used for the specified language, as JSON a config file could contain.
+    #[arg(long, value_name = "LANGUAGE")]
+    print_config: Option<config::LanguageName>,
+
+    /// Count files of each language dook finds here, and say which languages it can search.
+    #[arg(long)]
+    stats: bool,
+
+    /// List the languages dook can parse, each with the configs that cover it, the one used first.
+    #[arg(long)]
+    list_languages: bool,
+
+    /// Print a shell completion script to standard output.
+    #[arg(long, value_name = "SHELL")]
+    generate_completion: Option<clap_complete::Shell>,
+
+    /// Print a man page in roff format to standard output.
+    #[cfg(feature = "manpage")]
+    #[arg(long, hide = true)]
+    generate_manpage: bool,
+}
+
+/// Version plus statically linked languages and enabled features, one `key: values` line each.
+static LONG_VERSION: std::sync::LazyLock<String> = std::sync::LazyLock::new(|| {
+    use strum::IntoEnumIterator;
+    let languages: std::vec::Vec<&str> = config::LanguageName::iter().map(|l| l.into()).collect();
+    let features: std::vec::Vec<&str> = [
+        ("gitignore", cfg!(feature = "gitignore")),
+        ("manpage", cfg!(feature = "manpage")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect();
+    format!(
+        "{}\nlanguages: {}\nfeatures: {}",
+        env!("CARGO_PKG_VERSION"),
+        languages.join(" "),
+        features.join(" "),
+    )
+    .trim_end()
+    .to_string()
+});
+
+/// Write the parse tree of a file to `writer`, or given what a query captured in it, just those captures.
+fn dump_file(
+    writer: &mut impl std::io::Write,
+    file_info: &searches::ParsedFile,
+    captures: Option<&[searches::Capture]>,
+    use_color: bool,
+    show_positions: bool,
+) -> std::io::Result<()> {
+    match captures {
+        None => dumptree::dump_tree(
+            writer,
+            &file_info.tree,
+            file_info.source_code.as_slice(),
+            use_color,
+            show_positions,
+        )?,
+        Some(captures) => {
+            for capture in captures {
+                writeln!(
+                    writer,
+                    "@{} {} [{}:{}-{}:{}] {:?}",
+                    capture.name,
+                    capture.kind,
+                    capture.start.row + 1,
+                    capture.start.column + 1,
+                    capture.end.row + 1,
+                    capture.end.column + 1,
+                    capture.text,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compile the user's pattern, first escaping it if it's a fixed string.
+fn user_pattern(pattern: &str, fixed_strings: bool) -> std::io::Result<regex::Regex> {
+    let pattern = match fixed_strings {
+        true => regex::escape(pattern),
+        false => String::from(pattern),
+    };
+    regex::Regex::new(&pattern)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+}
+
+/// Nudge toward ignoring case when nothing matched, unless output looks meant for another program.
+/// Not for fixed strings, where `(?i)` would be searched for literally, nor when inverting,
+/// where ignoring case would only leave fewer names that don't match.
+fn suggest_ignoring_case(pattern: &regex::Regex, cli: &Cli) {
+    if !cli.only_names
+        && cli.plain == 0
+        && !cli.fixed_strings
+        && !cli.invert_match
+        && console::Term::stdout().is_term()
+        && !pattern.as_str().contains("(?i)")
+    {
+        eprintln!("No definitions matched; try starting the pattern with (?i) to ignore case.");
+    }
 }
 
 fn main() -> std::io::Result<std::process::ExitCode> {
+    match run() {
+        // whatever was reading our output is gone, which is its business, not an error
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(std::process::ExitCode::SUCCESS),
+        result => result,
+    }
+}
+
+fn run() -> std::io::Result<std::process::ExitCode> {
     use clap::Parser;
-    use os_str_bytes::OsStrBytes;
     use std::io::Write;
 
     env_logger::init();
 
     // grab cli args
     let cli = Cli::parse();
+
+    // check for completion-generation mode
+    if let Some(shell) = cli.generate_completion {
+        use clap::CommandFactory;
+        // clap_complete panics on write errors, so hand it a buffer and write that ourselves
+        let mut script = std::vec::Vec::new();
+        clap_complete::generate(shell, &mut Cli::command(), "dook", &mut script);
+        std::io::stdout().lock().write_all(&script)?;
+        return Ok(std::process::ExitCode::SUCCESS);
+    }
+    #[cfg(feature = "manpage")]
+    if cli.generate_manpage {
+        use clap::CommandFactory;
+        clap_mangen::Man::new(Cli::command()).render(&mut std::io::stdout())?;
+        return Ok(std::process::ExitCode::SUCCESS);
+    }
+
     let use_color = if cli.color != EnablementLevel::Auto {
         cli.color
     } else if console::colors_enabled() {
@@ -69,89 +430,293 @@ fn main() -> std::io::Result<std::process::ExitCode> {
         EnablementLevel::Never
     };
 
+    let parse_timeout = cli.parse_timeout_ms.map(std::time::Duration::from_millis);
+
     // check for dump-parse mode
     if let Some(dump_target) = cli.dump {
-        let file_info = searches::ParsedFile::from_filename(&dump_target)?;
-        dumptree::dump_tree(
-            &file_info.tree,
-            file_info.source_code.as_slice(),
-            use_color == EnablementLevel::Always,
-        );
+        if !std::path::Path::new(&dump_target).is_dir() {
+            let file_info = searches::ParsedFile::from_filename(&dump_target, parse_timeout)?;
+            let captures = match &cli.dump_query {
+                Some(query) => Some(
+                    file_info.run_query(&searches::compile_query(file_info.language_name, query)?),
+                ),
+                None => None,
+            };
+            dump_file(
+                &mut std::io::stdout().lock(),
+                &file_info,
+                captures.as_deref(),
+                use_color == EnablementLevel::Always,
+                cli.dump_positions,
+            )?;
+            return Ok(std::process::ExitCode::SUCCESS);
+        }
+        // for a directory, dump every file we can parse, each under a header like head(1)'s
+        let match_everything = regex::Regex::new("").unwrap();
+        // compiled once per language, including the ones it doesn't compile for
+        let mut queries: std::collections::HashMap<
+            config::LanguageName,
+            Option<tree_sitter::Query>,
+        > = Default::default();
+        let mut stdout = std::io::stdout().lock();
+        for path in walk::files_matching(
+            std::path::Path::new(&dump_target),
+            &match_everything,
+            cli.text,
+        )? {
+            let file_info = match searches::ParsedFile::from_filename(&path, parse_timeout) {
+                Ok(f) => f,
+                Err(_) => continue,
+            };
+            let captures = match &cli.dump_query {
+                Some(query) => {
+                    // the query is only valid in some languages; leave out files in the others
+                    let compiled = queries.entry(file_info.language_name).or_insert_with(|| {
+                        searches::compile_query(file_info.language_name, query)
+                            .inspect_err(|e| {
+                                log::info!("skipping {:?} files: {}", file_info.language_name, e)
+                            })
+                            .ok()
+                    });
+                    match compiled {
+                        Some(compiled) => Some(file_info.run_query(compiled)),
+                        None => continue,
+                    }
+                }
+                None => None,
+            };
+            writeln!(stdout, "==> {} <==", path.to_string_lossy())?;
+            dump_file(
+                &mut stdout,
+                &file_info,
+                captures.as_deref(),
+                use_color == EnablementLevel::Always,
+                cli.dump_positions,
+            )?;
+        }
         return Ok(std::process::ExitCode::SUCCESS);
     }
-    let mut current_pattern = match cli.pattern {
-        Some(pattern) => pattern.clone(),
-        None => {
+
+    // load config
+    let custom_configs = match cli.no_config {
+        true => std::vec::Vec::new(),
+        false => config::Config::load(cli.config.clone())?,
+    };
+    let default_config = config::Config::load_default();
+
+    // check for print-config mode, which picks a config the same way searching does
+    if let Some(language_name) = cli.print_config {
+        let Some((config, json)) = custom_configs
+            .iter()
+            .chain(std::iter::once(&default_config))
+            .find_map(|c| c.language_config_json(language_name).map(|j| (c, j)))
+        else {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
-                "pattern is required unless using --dump",
-            ))
+                format!(
+                    "No config contains definitions for language: {:?}",
+                    language_name
+                ),
+            ));
+        };
+        eprintln!("From {}:", config.source);
+        writeln!(std::io::stdout().lock(), "{}", json)?;
+        return Ok(std::process::ExitCode::SUCCESS);
+    }
+
+    // check for language-listing mode
+    if cli.list_languages {
+        use strum::IntoEnumIterator;
+        let mut languages: std::vec::Vec<(&str, config::LanguageName)> =
+            config::LanguageName::iter()
+                .map(|language_name| (language_name.into(), language_name))
+                .collect();
+        languages.sort_by_key(|(key, _)| *key);
+        let mut stdout = std::io::stdout().lock();
+        for (key, language_name) in languages {
+            let sources: std::vec::Vec<String> = custom_configs
+                .iter()
+                .chain(std::iter::once(&default_config))
+                .filter(|c| c.has_language(language_name))
+                .map(|c| c.source.to_string())
+                .collect();
+            match sources.is_empty() {
+                true => writeln!(stdout, "{}\tno config", key)?,
+                false => writeln!(stdout, "{}\t{}", key, sources.join(", "))?,
+            }
+        }
+        return Ok(std::process::ExitCode::SUCCESS);
+    }
+
+    // check for stats mode, which lists files the same way searching does
+    if cli.stats {
+        let filenames = if !cli.paths.is_empty() {
+            cli.paths.clone()
+        } else {
+            match candidate_files(None, cli.text)? {
+                Ok(filenames) => filenames,
+                Err(code) => return Ok(std::process::ExitCode::from(code)),
+            }
+        };
+        let mut counts: std::collections::BTreeMap<&str, usize> = Default::default();
+        for path in filenames.iter() {
+            let detected = match hyperpolyglot::detect(std::path::Path::new(path)) {
+                Ok(Some(detection)) => detection.language(),
+                _ => "unknown",
+            };
+            *counts.entry(detected).or_default() += 1;
+        }
+        // most files first; the sort is stable so ties stay alphabetical
+        let mut counts: std::vec::Vec<(&str, usize)> = counts.into_iter().collect();
+        counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        let width = counts
+            .first()
+            .map_or(1, |(_, count)| count.to_string().len());
+        let mut stdout = std::io::stdout().lock();
+        for (detected, count) in counts {
+            let support = match searches::language_name_for(detected) {
+                None => String::from("not supported"),
+                Some(language_name) => {
+                    let key: &str = language_name.into();
+                    match custom_configs
+                        .iter()
+                        .chain(std::iter::once(&default_config))
+                        .find(|c| c.has_language(language_name))
+                    {
+                        Some(c) => format!("{}, from {}", key, c.source),
+                        None => format!("{}, no config", key),
+                    }
+                }
+            };
+            writeln!(
+                stdout,
+                "{:>width$} {} ({})",
+                count,
+                detected,
+                support,
+                width = width
+            )?;
         }
+        return Ok(std::process::ExitCode::SUCCESS);
+    }
+
+    let mut current_pattern = match &cli.pattern {
+        Some(pattern) => user_pattern(pattern, cli.fixed_strings)?,
+        None => return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "pattern is required unless using --dump, --print-config, --stats, or --list-languages",
+        )),
     };
     let mut local_patterns: std::vec::Vec<regex::Regex> = vec![];
+    let mut current_qualifier: Option<String> = None;
+    let label = cli.label || cli.group_by == GroupBy::Name;
 
-    // load config
-    let custom_config = config::Config::load(cli.config)?;
-    let default_config = config::Config::load_default();
+    // keep parsed files around in case we recurse back into them
+    let mut parsed_files = searches::ParsedFileCache::new(PARSED_FILE_CACHE_SIZE);
 
     // store the result here
-    let mut print_ranges: Vec<(std::ffi::OsString, range_union::RangeUnion)> = Vec::new();
+    let mut print_ranges: Vec<(std::ffi::OsString, Option<String>, range_union::RangeUnion)> =
+        Vec::new();
+    let mut print_names: std::collections::BTreeMap<String, std::vec::Vec<String>> =
+        Default::default();
+    let mut print_counts: std::collections::BTreeMap<std::ffi::OsString, usize> =
+        Default::default();
+    let mut print_locations: std::vec::Vec<(std::ffi::OsString, String)> = std::vec::Vec::new();
+    // by path, since recursion can come back to the same files
+    let mut skipped_languages: std::collections::BTreeMap<
+        String,
+        std::collections::HashSet<std::ffi::OsString>,
+    > = Default::default();
+    // definitions queued by earlier passes, by file
+    let mut printed_spans: std::collections::HashMap<
+        std::ffi::OsString,
+        std::vec::Vec<std::ops::Range<usize>>,
+    > = Default::default();
+    let mut failed_paths: std::collections::HashSet<std::ffi::OsString> = Default::default();
+    let mut seen_names: std::collections::HashSet<String> = Default::default();
+    let mut shown_languages: std::collections::HashSet<std::ffi::OsString> = Default::default();
+    let mut warned_languages: std::collections::HashSet<config::LanguageName> = Default::default();
     loop {
-        // first-pass search with ripgrep
-        let mut rg = std::process::Command::new("rg");
-        let rg_output = rg
-            .arg("-l")
-            .arg("-0")
-            .arg(current_pattern.as_str())
-            .arg("./")
-            .stderr(std::process::Stdio::inherit())
-            .output()?;
-        if !rg_output.status.success() {
-            if let Some(e) = rg_output.status.code() {
-                return Ok(std::process::ExitCode::from(e as u8)); // truncate to 8 bits
+        // first-pass search with ripgrep, unless we were told which files to look in
+        let filenames = if !cli.paths.is_empty() {
+            cli.paths.clone()
+        } else {
+            // names that don't match can't be told apart by file contents
+            let content_pattern = (!cli.invert_match).then_some(&current_pattern);
+            match candidate_files(content_pattern, cli.text)? {
+                Ok(filenames) => filenames,
+                Err(code) => {
+                    if code == 1 && local_patterns.is_empty() {
+                        suggest_ignoring_case(&current_pattern, &cli);
+                    }
+                    return Ok(std::process::ExitCode::from(code));
+                }
             }
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("{}", rg_output.status),
-            ));
-        }
-        // TODO is this even actually the right way to convert stdout to OsStr?
-        let filenames: std::io::Result<std::vec::Vec<std::ffi::OsString>> = rg_output
-            .stdout
-            .split(|x| *x == 0)
-            .map(|x| match std::ffi::OsStr::from_io_bytes(x) {
-                None => Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    format!("{:?}", std::vec::Vec::from(x)),
-                )),
-                Some(y) => Ok(y.to_os_string()),
-            })
-            .filter(|f| match f {
-                Ok(f) => !f.is_empty(),
-                _ => true,
-            })
-            .collect();
-        let mut filenames = filenames?;
-        filenames.sort_unstable();
+        };
 
         // infer syntax, then search with tree_sitter
-        let mut recurse_defs: std::vec::Vec<String> = vec![];
-        local_patterns.push(
-            match regex::Regex::new(&(String::from("^") + current_pattern.as_str() + "$")) {
-                Ok(p) => p,
-                Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)),
-            },
-        );
+        let mut recurse_defs: std::vec::Vec<searches::RecurseName> = vec![];
+        let mut new_spans: std::vec::Vec<(std::ffi::OsString, std::ops::Range<usize>)> = vec![];
+        // anchor to whole names, except for the user's own pattern with --partial
+        let anchored = match cli.partial && local_patterns.is_empty() {
+            true => String::from(current_pattern.as_str()),
+            false => String::from("^(?:") + current_pattern.as_str() + ")$",
+        };
+        local_patterns.push(match regex::Regex::new(&anchored) {
+            Ok(p) => p,
+            Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)),
+        });
         let local_pattern = local_patterns.last().unwrap();
         for path in filenames {
-            let file_info = match searches::ParsedFile::from_filename(&path) {
-                Err(_) => continue, // TODO eprintln! every error that isn't a failure to parse
+            let file_info = match parsed_files.get_or_parse(&path, parse_timeout) {
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                    eprintln!(
+                        "Skipping {:?}: parsing took longer than {}ms",
+                        path,
+                        cli.parse_timeout_ms.unwrap_or_default()
+                    );
+                    continue;
+                }
+                Err(e)
+                    if e.get_ref()
+                        .is_some_and(|inner| inner.is::<searches::IncompatibleAbi>()) =>
+                {
+                    // every file in this language would fail the same way, so say so right away,
+                    // in words rather than the error's fields
+                    return Err(std::io::Error::new(e.kind(), e.to_string()));
+                }
+                Err(e) => {
+                    if let Some(searches::UnsupportedLanguage(language)) = e
+                        .get_ref()
+                        .and_then(|inner| inner.downcast_ref::<searches::UnsupportedLanguage>())
+                    {
+                        skipped_languages
+                            .entry(language.clone())
+                            .or_default()
+                            .insert(path.clone());
+                        if cli.show_language && shown_languages.insert(path.clone()) {
+                            eprintln!("{}: {} (not supported)", path.to_string_lossy(), language);
+                        }
+                    } else if !cli.paths.is_empty() && failed_paths.insert(path.clone()) {
+                        // the user asked for this file by name, so don't leave it out quietly
+                        eprintln!("{}: {}", path.to_string_lossy(), e);
+                    }
+                    continue;
+                }
                 Ok(f) => f,
             };
-            let language_info = custom_config
-                .as_ref()
-                .and_then(|c| c.get_language_info(file_info.language_name))
-                .or_else(|| default_config.get_language_info(file_info.language_name))
+            // once per file, even if recursion comes back to it
+            if cli.show_language && shown_languages.insert(path.clone()) {
+                let language: &str = file_info.language_name.into();
+                eprintln!("{}: {}", path.to_string_lossy(), language);
+            }
+            let (language_config, language_info) = custom_configs
+                .iter()
+                .chain(std::iter::once(&default_config))
+                .find_map(|c| {
+                    c.get_language_info(file_info.language_name)
+                        .map(|info| (c, info))
+                })
                 .ok_or_else(|| {
                     std::io::Error::new(
                         std::io::ErrorKind::InvalidInput,
@@ -160,81 +725,269 @@ fn main() -> std::io::Result<std::process::ExitCode> {
                             file_info.language_name
                         ),
                     )
-                })?
-                .map_err(|e| {
-                    std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}", e))
                 })?;
-            let (new_ranges, new_recurses) = searches::find_definition(
+            let language_info = language_info
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+            // config problems that don't stop us, once per language rather than once per file
+            if warned_languages.insert(file_info.language_name) {
+                for warning in language_info.warnings.iter() {
+                    log::warn!(
+                        "In {:?} patterns from {}: {}",
+                        file_info.language_name,
+                        language_config.source,
+                        warning
+                    );
+                }
+            }
+            if cli.only_names {
+                let find_names = match cli.all_matches {
+                    true => searches::find_name_sites,
+                    false => searches::find_names,
+                };
+                for (name, position) in find_names(
+                    file_info.source_code.as_slice(),
+                    &file_info.tree,
+                    &language_info,
+                    local_pattern,
+                    cli.invert_match,
+                ) {
+                    // keep the first file's location, same as the first definition within a file,
+                    // unless we want them all
+                    let location = format!("{}:{}", path.to_string_lossy(), position.row + 1);
+                    let locations = print_names.entry(name).or_default();
+                    if (cli.all_matches || locations.is_empty()) && !locations.contains(&location) {
+                        locations.push(location);
+                    }
+                }
+                continue;
+            }
+            let mut search_result = searches::find_definition(
                 file_info.source_code.as_slice(),
                 &file_info.tree,
                 &language_info,
                 local_pattern,
+                cli.invert_match,
                 true,
+                cli.full_scope,
             );
-            if !new_ranges.is_empty() {
-                print_ranges.push((path, new_ranges)); // TODO extend prev if new_ranges comes after in the same file
-                recurse_defs.extend(
-                    new_recurses.into_iter().filter(|name| {
-                        local_patterns.iter().all(|pattern| !pattern.is_match(name))
-                    }),
+            if let Some(qualifier) = &current_qualifier {
+                search_result.prefer_qualifier(qualifier);
+            }
+            // only the user's own search is about docs; whatever it calls is shown regardless
+            if let (Some(doc_match), 1) = (&cli.doc_match, local_patterns.len()) {
+                search_result.retain(|m| doc_match.is_match(&m.docs));
+            }
+            if cli.first {
+                // within a file, the earliest definition of each name not already shown wins
+                let mut firsts: std::collections::HashMap<String, tree_sitter::Point> =
+                    Default::default();
+                for definition in search_result.definitions.iter() {
+                    if !seen_names.contains(&definition.name) {
+                        firsts
+                            .entry(definition.name.clone())
+                            .and_modify(|p| *p = (*p).min(definition.position))
+                            .or_insert(definition.position);
+                    }
+                }
+                search_result.retain(|d| firsts.get(&d.name) == Some(&d.position));
+                seen_names.extend(firsts.into_keys());
+            }
+            if cli.count && !search_result.definitions.is_empty() {
+                *print_counts.entry(path.clone()).or_default() += search_result.definitions.len();
+            }
+            if cli.format == OutputFormat::Vimgrep {
+                let lines: std::vec::Vec<&[u8]> =
+                    file_info.source_code.split(|c| *c == b'\n').collect();
+                for definition in search_result.definitions.iter() {
+                    let line = lines
+                        .get(definition.position.row)
+                        .copied()
+                        .unwrap_or_default();
+                    print_locations.push((
+                        path.clone(),
+                        format!(
+                            "{}:{}:{}:{}",
+                            path.to_string_lossy(),
+                            definition.position.row + 1,
+                            // skipping any byte order mark, as ripgrep does and as vim shows the line
+                            definition.position.column + 1,
+                            String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line)),
+                        ),
+                    ));
+                }
+            }
+            // leave out definitions an earlier pass already queued, like one we recursed back into,
+            // but leave this pass's own results alone even if they overlap each other
+            if let Some(printed) = printed_spans.get(&path) {
+                search_result.drop_printed(printed);
+            }
+            new_spans.extend(
+                search_result
+                    .definitions
+                    .iter()
+                    .map(|d| (path.clone(), d.range.clone())),
+            );
+            if label {
+                // one entry per name, with repeat definitions (such as overloads) merged into it
+                let mut named_ranges: Vec<(String, range_union::RangeUnion)> = Vec::new();
+                for definition in search_result.definitions {
+                    let definition_ranges = definition.ranges();
+                    match named_ranges
+                        .iter_mut()
+                        .find(|(name, _)| *name == definition.name)
+                    {
+                        Some((_, ranges)) => {
+                            for range in definition_ranges.iter() {
+                                ranges.push(range);
+                            }
+                        }
+                        None => named_ranges.push((definition.name, definition_ranges)),
+                    }
+                }
+                print_ranges.extend(
+                    named_ranges
+                        .into_iter()
+                        .filter(|(_, ranges)| !ranges.is_empty())
+                        .map(|(name, ranges)| (path.clone(), Some(name), ranges)),
                 );
             }
+            if !search_result.ranges.is_empty() {
+                if !label {
+                    print_ranges.push((path, None, search_result.ranges)); // TODO extend prev if new_ranges comes after in the same file
+                }
+                recurse_defs.extend(search_result.recurse_names.into_iter().filter(|call| {
+                    local_patterns
+                        .iter()
+                        .all(|pattern| !pattern.is_match(&call.name))
+                }));
+            }
         }
-        recurse_defs.dedup();
-        if cli.recurse && recurse_defs.len() == 1 {
-            current_pattern = regex::Regex::new(&regex::escape(&recurse_defs[0])).unwrap();
-        } else {
-            break;
+        for (path, span) in new_spans {
+            printed_spans.entry(path).or_default().push(span);
+        }
+        match searches::recurse_target(&recurse_defs) {
+            Some(target) if cli.recurse => {
+                current_pattern = regex::Regex::new(&regex::escape(&target.name)).unwrap();
+                current_qualifier = target.qualifier;
+            }
+            _ => break,
         }
     }
 
-    // set up paging if requested
-    let enable_paging = if cli.paging != EnablementLevel::Auto {
-        cli.paging == EnablementLevel::Always
+    if print_ranges.is_empty()
+        && print_names.is_empty()
+        && print_counts.is_empty()
+        && print_locations.is_empty()
+    {
+        suggest_ignoring_case(&local_patterns[0], &cli);
+    }
+
+    if cli.sort == SortOrder::Path {
+        // stable sorts, so each file's results stay in the order they were found
+        print_ranges.sort_by(|a, b| a.0.cmp(&b.0));
+        print_locations.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+    if cli.group_by == GroupBy::Name {
+        // also stable, so each name's files stay in the order chosen above
+        print_ranges.sort_by(|a, b| a.1.cmp(&b.1));
+    }
+
+    if cli.only_names {
+        // names go out all at once at the end so they come out sorted
+        let mut stdout = std::io::stdout().lock();
+        for (name, locations) in print_names {
+            for location in locations {
+                match cli.with_location || cli.all_matches {
+                    true => writeln!(stdout, "{}\t{}", name, location)?,
+                    false => writeln!(stdout, "{}", name)?,
+                }
+            }
+        }
+    } else if cli.count {
+        // counts skip paging and styling
+        let mut stdout = std::io::stdout().lock();
+        for (path, count) in print_counts.iter() {
+            writeln!(stdout, "{}:{}", path.to_string_lossy(), count)?;
+        }
+        writeln!(stdout, "total:{}", print_counts.values().sum::<usize>())?;
+    } else if cli.format == OutputFormat::Vimgrep {
+        // so do locations, which are for editors to read
+        let mut stdout = std::io::stdout().lock();
+        for (_path, location) in print_locations {
+            writeln!(stdout, "{}", location)?;
+        }
     } else {
-        cli.plain < 2 && console::Term::stdout().is_term()
-    };
-    let mut pager = paging::MaybePager::new(enable_paging);
-    let bat_size = console::Term::stdout().size_checked();
-    for (path, ranges) in print_ranges.iter() {
-        let mut cmd = std::process::Command::new("bat");
-        let cmd = cmd
-            .arg("--paging=never")
-            .arg(format!("--color={:?}", use_color).to_lowercase());
-        let cmd = match bat_size {
-            Some((_rows, cols)) => cmd.arg(format!("--terminal-width={}", cols)),
-            None => cmd,
-        };
-        let cmd = match cli.plain {
-            0 => cmd,
-            _ => cmd.arg("--plain"),
+        // set up paging if requested
+        let enable_paging = if cli.paging != EnablementLevel::Auto {
+            cli.paging == EnablementLevel::Always
+        } else {
+            cli.plain < 2 && console::Term::stdout().is_term()
         };
-        let cmd = cmd
-            .args(
-                ranges
-                    .iter_filling_gaps(1) // snip indicator - 8< - takes 1 line anyway
-                    .map(|x| format!("--line-range={}:{}", x.start + 1, x.end)), // bat end is inclusive
-            )
-            .arg(path);
-        let output = match cmd.stderr(std::process::Stdio::inherit()).output() {
-            Ok(output) => output.stdout,
-            Err(e) => std::vec::Vec::from(format!("Error reading {:?}: {}", path, e)),
+        let output_options = outputs::OutputOptions {
+            use_color: use_color == EnablementLevel::Always,
+            plain: cli.plain,
+            terminal_size: outputs::terminal_size(cli.width),
+            wrap: cli.wrap,
+            indent_continuations: true,
+            snip: outputs::SnipOptions {
+                gap: cli.snip_gap,
+                file_rule: cli.file_rule.clone(),
+                snip_rule: cli.snip_rule.clone(),
+            },
         };
-        if let Err(e) = pager.write_all(&output) {
-            if e.kind() == std::io::ErrorKind::BrokenPipe {
-                // stdout is gone so let's just leave quietly
-                return Ok(std::process::ExitCode::SUCCESS);
+        let mut pager = paging::MaybePager::new(enable_paging);
+        for (path, label, ranges) in print_ranges.iter() {
+            if let Err(e) =
+                outputs::write_ranges(path, label.as_deref(), ranges, &output_options, &mut pager)
+            {
+                if e.kind() == std::io::ErrorKind::BrokenPipe {
+                    // stdout is gone so let's just leave quietly
+                    return Ok(std::process::ExitCode::SUCCESS);
+                }
+                break;
             }
-            break;
+        }
+        // wait for pager
+        match pager.wait() {
+            Ok(0) => (),
+            Ok(status) => println!("Pager exited {}", status),
+            Err(e) => println!("Pager died or vanished: {}", e),
         }
     }
-    // wait for pager
-    match pager.wait() {
-        Ok(0) => (),
-        Ok(status) => println!("Pager exited {}", status),
-        Err(e) => println!("Pager died or vanished: {}", e),
+
+    // explain why some files might have been left out
+    for (language, paths) in skipped_languages.iter() {
+        let count = paths.len();
+        eprintln!(
+            "Skipped {} {} file{}: language not supported",
+            count,
+            language,
+            if count == 1 { "" } else { "s" }
+        );
+    }
+
+    // like ripgrep, an error trumps whether anything matched
+    if !failed_paths.is_empty() {
+        return Ok(std::process::ExitCode::from(2));
     }
 
     // yeah yeah whatever
     Ok(std::process::ExitCode::SUCCESS)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_strings() {
+        for name in ["operator[]", "Vec<T>", "a.b", "(?i)x"] {
+            let pattern = user_pattern(name, true).unwrap();
+            assert!(pattern.is_match(name));
+            assert_eq!(pattern.find(name).unwrap().as_str(), name);
+        }
+        assert!(!user_pattern("a.b", true).unwrap().is_match("axb"));
+        assert!(user_pattern("a.b", false).unwrap().is_match("axb"));
+        assert!(user_pattern("operator[]", false).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+use crate::{range_union, searches};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WrapMode {
+    #[default]
+    Auto,
+    Never,
+    Character,
+}
+
+/// How to mark the lines left out between excerpts.
+pub struct SnipOptions {
+    /// Show gaps of at most this many lines instead of snipping them.
+    pub gap: usize,
+    /// Rule drawn around each file name in plain output.
+    pub file_rule: String,
+    /// Rule drawn between excerpts from the same file in plain output.
+    pub snip_rule: String,
+}
+
+impl Default for SnipOptions {
+    fn default() -> Self {
+        Self {
+            gap: 1, // snip indicator - 8< - takes 1 line anyway
+            file_rule: String::from("="),
+            snip_rule: String::from("-"),
+        }
+    }
+}
+
+pub struct OutputOptions {
+    pub use_color: bool,
+    pub plain: u8,
+    pub terminal_size: Option<(u16, u16)>,
+    pub wrap: WrapMode,
+    /// When wrapping plain output, start continuation lines at the wrapped line's indentation, like bat does.
+    pub indent_continuations: bool,
+    pub snip: SnipOptions,
+}
+
+/// Rows and columns to render for, with `width` overriding the columns.
+/// A pager writes to our own stdout, so measuring stdout is right whether or not we page;
+/// when stdout isn't a terminal (say, a pipe), the size is unknown unless `width` is given.
+/// Measured once per run, so later resizes of whatever reads a pipe don't apply.
+pub fn terminal_size(width: Option<u16>) -> Option<(u16, u16)> {
+    let measured = console::Term::stdout().size_checked();
+    match width {
+        Some(cols) => Some((measured.map_or(0, |(rows, _cols)| rows), cols)),
+        None => measured,
+    }
+}
+
+/// The bat executable to run, which `DOOK_BAT` can override.
+fn bat_program() -> std::ffi::OsString {
+    std::env::var_os("DOOK_BAT").unwrap_or_else(|| "bat".into())
+}
+
+/// Whether `bat_program()` runs, checked once per process (and warned about once if not).
+fn has_bat() -> bool {
+    static HAS_BAT: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *HAS_BAT.get_or_init(|| {
+        match std::process::Command::new(bat_program())
+            .arg("-V")
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+        {
+            Ok(status) => status.success(),
+            Err(e) => {
+                log::warn!("bat not found, falling back to plain output: {}", e);
+                false
+            }
+        }
+    })
+}
+
+/// Print `ranges` from the file at `path`, with `label` (if any) after the file name in the header.
+pub fn write_ranges(
+    path: &std::ffi::OsStr,
+    label: Option<&str>,
+    ranges: &range_union::RangeUnion,
+    options: &OutputOptions,
+    writer: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    if has_bat() {
+        write_ranges_with_bat(path, label, ranges, options, writer)
+    } else {
+        // bat detects UTF-16 by itself, but here we transcode the same way parsing did
+        match std::fs::read(path) {
+            Ok(contents) => write_ranges_with_std_io(
+                &header_name(path, label),
+                &searches::decode_utf16(&contents).unwrap_or(contents)[..],
+                ranges,
+                options,
+                writer,
+            ),
+            Err(e) => writer.write_all(format!("Error reading {:?}: {}", path, e).as_bytes()),
+        }
+    }
+}
+
+pub fn write_ranges_with_bat(
+    path: &std::ffi::OsStr,
+    label: Option<&str>,
+    ranges: &range_union::RangeUnion,
+    options: &OutputOptions,
+    writer: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let mut cmd = std::process::Command::new(bat_program());
+    let cmd = cmd.arg("--paging=never").arg(if options.use_color {
+        "--color=always"
+    } else {
+        "--color=never"
+    });
+    let cmd = match options.terminal_size {
+        Some((_rows, cols)) => cmd.arg(format!("--terminal-width={}", cols)),
+        None => cmd,
+    };
+    let cmd = match options.plain {
+        0 => cmd,
+        _ => cmd.arg("--plain"),
+    };
+    let cmd = match label {
+        Some(_) => cmd.arg("--file-name").arg(header_name(path, label)),
+        None => cmd,
+    };
+    let cmd = cmd.arg(match options.wrap {
+        WrapMode::Auto => "--wrap=auto",
+        WrapMode::Never => "--wrap=never",
+        WrapMode::Character => "--wrap=character",
+    });
+    let cmd = cmd.args(bat_line_ranges(ranges, options)).arg(path);
+    let output = match cmd.stderr(std::process::Stdio::inherit()).output() {
+        Ok(output) => output.stdout,
+        Err(e) => std::vec::Vec::from(format!("Error reading {:?}: {}", path, e)),
+    };
+    writer.write_all(&output)
+}
+
+/// bat's `--line-range` arguments, merged across the same gaps the plain output fills in,
+/// so both show the same lines and snip in the same places.
+fn bat_line_ranges(
+    ranges: &range_union::RangeUnion,
+    options: &OutputOptions,
+) -> std::vec::Vec<String> {
+    ranges
+        .iter_filling_gaps(options.snip.gap)
+        .map(|x| format!("--line-range={}:{}", x.start + 1, x.end)) // bat end is inclusive
+        .collect()
+}
+
+pub fn write_ranges_with_std_io(
+    name: &str,
+    mut reader: impl std::io::BufRead,
+    ranges: &range_union::RangeUnion,
+    options: &OutputOptions,
+    writer: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let cols = match options.terminal_size {
+        Some((_rows, cols)) => cols as usize,
+        None => 40,
+    };
+    let sep1 = horizontal_rule(&options.snip.file_rule, cols);
+    let sep2 = horizontal_rule(&options.snip.snip_rule, cols);
+    let max_line_number = ranges.end().unwrap_or_default();
+    let gutter_width = max_line_number.to_string().len();
+    // plain enough means nothing but the lines themselves, for piping elsewhere
+    let raw = options.plain >= 3;
+    let wrap_width = match (options.wrap, options.terminal_size) {
+        _ if raw => None,
+        (WrapMode::Never, _) | (WrapMode::Auto, None) => None,
+        (WrapMode::Auto | WrapMode::Character, _) => Some(match options.plain {
+            0 => cols.saturating_sub(gutter_width + 3),
+            _ => cols,
+        }),
+    };
+    if !raw {
+        writeln!(writer, "{}\n{}\n{}", sep1, name, sep1)?;
+    }
+    let mut line = std::vec::Vec::<u8>::new();
+    let mut line_number: usize = 0;
+    'ranges: for (i, range) in ranges.iter_filling_gaps(options.snip.gap).enumerate() {
+        // context can be widened past the end of the file, so clamp to the lines actually there
+        // and only start a range once we know it has a line to show
+        while line_number < range.end {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break 'ranges;
+            }
+            line_number += 1;
+            if line_number <= range.start {
+                continue;
+            }
+            if i > 0 && line_number == range.start + 1 && !raw {
+                writeln!(writer, "{}", sep2)?;
+            }
+            // strip \n and also \r so windows line endings don't garble the terminal
+            let content = match line.strip_suffix(b"\n") {
+                Some(l) => l.strip_suffix(b"\r").unwrap_or(l),
+                None => &line,
+            };
+            // and the byte order mark, which parsing didn't see either
+            let content = match line_number {
+                1 => content.strip_prefix(searches::UTF8_BOM).unwrap_or(content),
+                _ => content,
+            };
+            let lossy_content;
+            let mut indent: &[u8] = &[];
+            let chunks = match wrap_width {
+                None => vec![content],
+                Some(w) => {
+                    lossy_content = String::from_utf8_lossy(content);
+                    let unindented = lossy_content.trim_start_matches([' ', '\t']);
+                    // unless the indentation alone would fill the line
+                    if options.indent_continuations && lossy_content.len() - unindented.len() < w {
+                        indent = &content[..lossy_content.len() - unindented.len()];
+                    }
+                    wrap_line(&lossy_content, w, indent.len())
+                        .into_iter()
+                        .map(str::as_bytes)
+                        .collect()
+                }
+            };
+            for (j, chunk) in chunks.into_iter().enumerate() {
+                if options.plain == 0 {
+                    match j {
+                        0 => write!(writer, "{:>width$} | ", line_number, width = gutter_width)?,
+                        _ => write!(writer, "{:>width$} | ", "", width = gutter_width)?,
+                    }
+                }
+                if j > 0 {
+                    writer.write_all(indent)?;
+                }
+                writer.write_all(chunk)?;
+                writeln!(writer)?;
+            }
+        }
+    }
+    match raw {
+        true => Ok(()),
+        false => writeln!(writer, "{}", sep1),
+    }
+}
+
+/// What to call the file in output headers.
+fn header_name(path: &std::ffi::OsStr, label: Option<&str>) -> String {
+    match label {
+        Some(label) => format!("{}: {}", path.to_string_lossy(), label),
+        None => path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Repeat `fill` to span `cols` terminal cells, without going over.
+fn horizontal_rule(fill: &str, cols: usize) -> String {
+    use unicode_width::UnicodeWidthStr;
+    match fill.width() {
+        0 => String::new(),
+        w => fill.repeat(cols / w),
+    }
+}
+
+/// Split `line` into pieces at most `cols` terminal cells wide, each holding at least one character.
+/// Pieces after the first leave room for `indent` cells to go in front of them.
+fn wrap_line(line: &str, cols: usize, indent: usize) -> std::vec::Vec<&str> {
+    use unicode_width::UnicodeWidthChar;
+    let mut pieces = std::vec::Vec::new();
+    let mut piece_start: usize = 0;
+    let mut piece_width: usize = 0;
+    for (i, c) in line.char_indices() {
+        let w = c.width().unwrap_or(1);
+        let piece_cols = match pieces.is_empty() {
+            true => cols,
+            false => cols.saturating_sub(indent),
+        };
+        if piece_width + w > piece_cols && i > piece_start {
+            pieces.push(&line[piece_start..i]);
+            piece_start = i;
+            piece_width = 0;
+        }
+        piece_width += w;
+    }
+    pieces.push(&line[piece_start..]);
+    pieces
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+
+    fn test_options(plain: u8) -> OutputOptions {
+        OutputOptions {
+            use_color: false,
+            plain,
+            terminal_size: Some((24, 10)),
+            wrap: WrapMode::Auto,
+            indent_continuations: true,
+            snip: Default::default(),
+        }
+    }
+
+    fn std_io_output(source: &[u8], ranges: &[std::ops::Range<usize>]) -> String {
+        std_io_output_plain(source, ranges, 0)
+    }
+
+    fn std_io_output_plain(source: &[u8], ranges: &[std::ops::Range<usize>], plain: u8) -> String {
+        let mut range_union = range_union::RangeUnion::default();
+        range_union.extend(ranges);
+        let mut output = std::vec::Vec::<u8>::new();
+        write_ranges_with_std_io(
+            "test",
+            source,
+            &range_union,
+            &test_options(plain),
+            &mut output,
+        )
+        .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn rule_width() {
+        assert_eq!(horizontal_rule("-", 5), "-----");
+        assert_eq!(horizontal_rule("＝", 5), "＝＝");
+        assert_eq!(horizontal_rule("-=", 5), "-=-=");
+        assert_eq!(horizontal_rule("", 5), "");
+    }
+
+    #[test]
+    fn wrap_width() {
+        assert_eq!(wrap_line("abcdefg", 3, 0), vec!["abc", "def", "g"]);
+        assert_eq!(wrap_line("あいう", 4, 0), vec!["あい", "う"]);
+        assert_eq!(wrap_line("あいう", 1, 0), vec!["あ", "い", "う"]);
+        assert_eq!(wrap_line("", 3, 0), vec![""]);
+        assert_eq!(wrap_line("  abcdefg", 4, 2), vec!["  ab", "cd", "ef", "g"]);
+    }
+
+    #[test]
+    fn std_io_wrap() {
+        assert_eq!(
+            std_io_output(b"one\ntwo three four\n", &[0..2]),
+            "==========\ntest\n==========\n1 | one\n2 | two th\n  | ree fo\n  | ur\n==========\n",
+        );
+    }
+
+    #[test]
+    fn std_io_past_eof() {
+        assert_eq!(
+            std_io_output(b"one\ntwo\nthree\nfour\nfive", &[0..1, 3..9]),
+            "==========\ntest\n==========\n1 | one\n----------\n4 | four\n5 | five\n==========\n",
+        );
+        assert_eq!(
+            std_io_output(b"one\ntwo\nthree\n", &[0..1, 5..9]),
+            "==========\ntest\n==========\n1 | one\n==========\n",
+        );
+    }
+
+    #[test]
+    fn std_io_raw() {
+        assert_eq!(
+            std_io_output_plain(b"one\ntwo\nthree\nfour\n", &[0..1, 3..4], 3),
+            "one\nfour\n",
+        );
+        // lines too long for the terminal stay whole
+        assert_eq!(
+            std_io_output_plain(b"one two three four\n", &[0..1], 3),
+            "one two three four\n",
+        );
+    }
+
+    #[test]
+    fn bat_and_std_io_snip_alike() {
+        // a one-line gap is shown, a two-line gap is snipped, by both
+        let ranges = [0..1, 2..3, 5..6];
+        let mut range_union = range_union::RangeUnion::default();
+        range_union.extend(&ranges);
+        assert_eq!(
+            bat_line_ranges(&range_union, &test_options(0)),
+            vec!["--line-range=1:3", "--line-range=6:6"]
+        );
+        assert_eq!(
+            std_io_output_plain(b"1\n2\n3\n4\n5\n6\n7\n", &ranges, 3),
+            "1\n2\n3\n6\n"
+        );
+        assert_eq!(
+            std_io_output(b"1\n2\n3\n4\n5\n6\n7\n", &ranges),
+            "==========\ntest\n==========\n1 | 1\n2 | 2\n3 | 3\n----------\n6 | 6\n==========\n",
+        );
+    }
+
+    #[test]
+    fn std_io_wrap_indent() {
+        assert_eq!(
+            std_io_output(b"  two three\n", &[0..1]),
+            "==========\ntest\n==========\n1 |   two \n  |   thre\n  |   e\n==========\n",
+        );
+        assert_eq!(
+            std_io_output_plain(b"  two three\n", &[0..1], 2),
+            "==========\ntest\n==========\n  two thre\n  e\n==========\n",
+        );
+    }
+
+    #[test]
+    fn std_io_crlf() {
+        assert_eq!(
+            std_io_output(b"one\r\ntwo\r\nthree\r\nfour\r\n", &[0..1, 3..4]),
+            "==========\ntest\n==========\n1 | one\n----------\n4 | four\n==========\n",
+        );
+    }
+
+    #[test]
+    fn std_io_bom() {
+        assert_eq!(
+            std_io_output_plain(b"\xef\xbb\xbfone\ntwo\n", &[0..2], 3),
+            "one\ntwo\n",
+        );
+    }
+}
@@ -4,12 +4,25 @@ pub struct MaybePager {
 
 impl MaybePager {
     pub fn new(enable_paging: bool) -> Self {
-        let pager = if enable_paging {
-            let mut pager_program = std::process::Command::new(match std::env::var_os("PAGER") {
-                Some(value) => value,
-                None => std::ffi::OsString::from("less"),
-            });
-            match (if pager_program.get_program() == "less" {
+        let pager_name = match (enable_paging, std::env::var_os("PAGER")) {
+            (false, _) => None,
+            (true, Some(value)) => Some(value),
+            // less is rarely installed on windows, but more always is
+            (true, None) => ["less", "more"]
+                .into_iter()
+                .find(|p| is_on_path(p))
+                .map(std::ffi::OsString::from),
+        };
+        let pager = if let Some(pager_name) = pager_name {
+            let (program, args) = split_command(&pager_name);
+            let mut pager_program = std::process::Command::new(program);
+            pager_program.args(args);
+            // someone who set LESS has already said how they like less to behave
+            let is_unconfigured_less = std::path::Path::new(pager_program.get_program())
+                .file_stem()
+                .is_some_and(|stem| stem == "less")
+                && std::env::var_os("LESS").is_none();
+            match (if is_unconfigured_less {
                 pager_program.arg("-RF")
             } else {
                 &mut pager_program
@@ -37,10 +50,7 @@ impl MaybePager {
                 match child.wait() {
                     Ok(status) => match status.code() {
                         Some(c) => Ok(c),
-                        None => Err(std::io::Error::new(
-                            std::io::ErrorKind::Other,
-                            "Unknown exit status",
-                        )),
+                        None => Err(std::io::Error::other("Unknown exit status")),
                     },
                     Err(e) => Err(e),
                 }
@@ -71,3 +81,62 @@ impl std::io::Write for MaybePager {
         }
     }
 }
+
+fn is_on_path(program: &str) -> bool {
+    // on windows, `more` is really more.com, so try every extension that runs without being typed
+    let extensions: std::vec::Vec<String> = match cfg!(windows) {
+        false => vec![],
+        true => std::env::var("PATHEXT")
+            .unwrap_or_else(|_| String::from(".COM;.EXE;.BAT;.CMD"))
+            .split(';')
+            .filter(|ext| !ext.is_empty())
+            .map(|ext| ext.trim_start_matches('.').to_owned())
+            .collect(),
+    };
+    match std::env::var_os("PATH") {
+        None => false,
+        Some(paths) => std::env::split_paths(&paths).any(|dir| {
+            let candidate = dir.join(program);
+            candidate.is_file()
+                || extensions
+                    .iter()
+                    .any(|ext| candidate.with_extension(ext).is_file())
+        }),
+    }
+}
+
+/// Split a command line like `less -R` into a program and its arguments, shell-style.
+/// Anything that doesn't split cleanly is taken to be the name of the program.
+fn split_command(
+    command: &std::ffi::OsStr,
+) -> (std::ffi::OsString, std::vec::Vec<std::ffi::OsString>) {
+    match command.to_str().and_then(shlex::split) {
+        Some(words) if !words.is_empty() => {
+            let mut words = words.into_iter().map(std::ffi::OsString::from);
+            (words.next().unwrap(), words.collect())
+        }
+        _ => (command.to_os_string(), vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_pager_command() {
+        assert_eq!(split_command("less".as_ref()), ("less".into(), vec![]));
+        assert_eq!(
+            split_command("less -R -X".as_ref()),
+            ("less".into(), vec!["-R".into(), "-X".into()])
+        );
+        assert_eq!(
+            split_command("'/opt/my pager/bin/less' --prompt=\"a b\"".as_ref()),
+            ("/opt/my pager/bin/less".into(), vec!["--prompt=a b".into()])
+        );
+        assert_eq!(
+            split_command("less 'oops".as_ref()),
+            ("less 'oops".into(), vec![])
+        );
+    }
+}
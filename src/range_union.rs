@@ -1,15 +1,56 @@
+/// How many intervals a `RangeUnion` holds before it needs a map, which covers most single definitions.
+const INLINE_CAPACITY: usize = 4;
+
 #[derive(Default)]
 pub struct RangeUnion {
-    ends_by_start: std::collections::BTreeMap<usize, usize>,
+    ends_by_start: EndsByStart,
+}
+
+enum EndsByStart {
+    /// the first few intervals, sorted by start, no two with the same start
+    Inline {
+        len: usize,
+        intervals: [(usize, usize); INLINE_CAPACITY],
+    },
+    Spilled(std::collections::BTreeMap<usize, usize>),
+}
+
+impl Default for EndsByStart {
+    fn default() -> Self {
+        EndsByStart::Inline {
+            len: 0,
+            intervals: [(0, 0); INLINE_CAPACITY],
+        }
+    }
 }
 
 impl RangeUnion {
     pub fn push(&mut self, range: impl std::borrow::Borrow<std::ops::Range<usize>>) {
         let range = range.borrow();
-        self.ends_by_start
-            .entry(range.start)
-            .and_modify(|e| *e = (*e).max(range.end))
-            .or_insert(range.end);
+        match &mut self.ends_by_start {
+            EndsByStart::Inline { len, intervals } => {
+                match intervals[..*len].binary_search_by_key(&range.start, |&(start, _)| start) {
+                    Ok(i) => intervals[i].1 = intervals[i].1.max(range.end),
+                    Err(i) if *len < INLINE_CAPACITY => {
+                        intervals[i..=*len].rotate_right(1);
+                        intervals[i] = (range.start, range.end);
+                        *len += 1;
+                    }
+                    Err(_) => {
+                        let mut ends_by_start: std::collections::BTreeMap<usize, usize> =
+                            intervals.iter().copied().collect();
+                        ends_by_start.insert(range.start, range.end);
+                        self.ends_by_start = EndsByStart::Spilled(ends_by_start);
+                    }
+                }
+            }
+            EndsByStart::Spilled(ends_by_start) => {
+                ends_by_start
+                    .entry(range.start)
+                    .and_modify(|e| *e = (*e).max(range.end))
+                    .or_insert(range.end);
+            }
+        }
     }
 
     pub fn extend(&mut self, ranges: impl AsRef<[std::ops::Range<usize>]>) {
@@ -18,8 +59,11 @@ impl RangeUnion {
         }
     }
 
-    pub fn iter_filling_gaps(&self, gap_size: usize) -> RangeUnionIterator {
-        let mut iterator = self.ends_by_start.iter();
+    pub fn iter_filling_gaps(&self, gap_size: usize) -> RangeUnionIterator<'_> {
+        let mut iterator = match &self.ends_by_start {
+            EndsByStart::Inline { len, intervals } => Intervals::Inline(intervals[..*len].iter()),
+            EndsByStart::Spilled(ends_by_start) => Intervals::Spilled(ends_by_start.iter()),
+        };
         let first_interval = iterator.next();
         RangeUnionIterator {
             position: iterator,
@@ -28,12 +72,35 @@ impl RangeUnion {
         }
     }
 
-    pub fn iter(&self) -> RangeUnionIterator {
+    pub fn iter(&self) -> RangeUnionIterator<'_> {
         self.iter_filling_gaps(0)
     }
 
+    /// Where the earliest range starts, or None if there are no ranges.
+    pub fn start(&self) -> Option<usize> {
+        match &self.ends_by_start {
+            EndsByStart::Inline { len, intervals } => {
+                intervals[..*len].first().map(|&(start, _)| start)
+            }
+            EndsByStart::Spilled(ends_by_start) => ends_by_start.keys().next().copied(),
+        }
+    }
+
+    /// Where the latest range ends, or None if there are no ranges.
+    pub fn end(&self) -> Option<usize> {
+        match &self.ends_by_start {
+            EndsByStart::Inline { len, intervals } => {
+                intervals[..*len].iter().map(|&(_, end)| end).max()
+            }
+            EndsByStart::Spilled(ends_by_start) => ends_by_start.values().max().copied(),
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.ends_by_start.is_empty()
+        match &self.ends_by_start {
+            EndsByStart::Inline { len, .. } => *len == 0,
+            EndsByStart::Spilled(ends_by_start) => ends_by_start.is_empty(),
+        }
     }
 }
 
@@ -45,9 +112,25 @@ impl<'it> IntoIterator for &'it RangeUnion {
     }
 }
 
+/// Intervals in order of start, wherever they're stored.
+enum Intervals<'it> {
+    Inline(std::slice::Iter<'it, (usize, usize)>),
+    Spilled(std::collections::btree_map::Iter<'it, usize, usize>),
+}
+
+impl Iterator for Intervals<'_> {
+    type Item = (usize, usize);
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Intervals::Inline(iterator) => iterator.next().copied(),
+            Intervals::Spilled(iterator) => iterator.next().map(|(&start, &end)| (start, end)),
+        }
+    }
+}
+
 pub struct RangeUnionIterator<'it> {
-    position: std::collections::btree_map::Iter<'it, usize, usize>,
-    current_interval: Option<(&'it usize, &'it usize)>,
+    position: Intervals<'it>,
+    current_interval: Option<(usize, usize)>,
     fill_gaps: usize,
 }
 
@@ -56,13 +139,13 @@ impl Iterator for RangeUnionIterator<'_> {
     fn next(&mut self) -> Option<Self::Item> {
         match self.current_interval {
             None => None,
-            Some((&first_start, &first_end)) => {
+            Some((first_start, first_end)) => {
                 let mut farthest_end: usize = first_end;
                 loop {
                     self.current_interval = self.position.next();
                     match self.current_interval {
                         None => break Some(first_start..farthest_end),
-                        Some((&start, &end)) => {
+                        Some((start, end)) => {
                             if start <= farthest_end + self.fill_gaps {
                                 farthest_end = farthest_end.max(end);
                             } else {
@@ -75,3 +158,67 @@ impl Iterator for RangeUnionIterator<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What a union of `ranges` should iterate as, worked out the slow way.
+    fn expected(ranges: &[std::ops::Range<usize>], gap_size: usize) -> Vec<std::ops::Range<usize>> {
+        let mut sorted = ranges.to_vec();
+        sorted.sort_by_key(|r| r.start);
+        let mut merged: Vec<std::ops::Range<usize>> = Vec::new();
+        for range in sorted {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end + gap_size => {
+                    last.end = last.end.max(range.end)
+                }
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
+    #[test]
+    fn inline_and_spilled_agree() {
+        let ranges = [
+            20..22,
+            0..3,
+            10..12,
+            0..5,
+            30..31,
+            6..8,
+            11..15,
+            40..45,
+            24..26,
+        ];
+        for count in 0..=ranges.len() {
+            let mut union = RangeUnion::default();
+            union.extend(&ranges[..count]);
+            for gap_size in [0, 1, 3] {
+                assert_eq!(
+                    union.iter_filling_gaps(gap_size).collect::<Vec<_>>(),
+                    expected(&ranges[..count], gap_size),
+                    "first {} ranges, gaps up to {}",
+                    count,
+                    gap_size
+                );
+            }
+            assert_eq!(union.is_empty(), count == 0);
+            assert_eq!(union.end(), ranges[..count].iter().map(|r| r.end).max());
+        }
+    }
+
+    #[test]
+    fn start_and_end() {
+        let mut union = RangeUnion::default();
+        assert_eq!((union.start(), union.end()), (None, None));
+        union.push(5..9);
+        assert_eq!((union.start(), union.end()), (Some(5), Some(9)));
+        // overlapping, and one nested inside the other
+        union.extend([3..6, 6..7]);
+        assert_eq!((union.start(), union.end()), (Some(3), Some(9)));
+        union.extend([20..21, 1..2, 5..12]);
+        assert_eq!((union.start(), union.end()), (Some(1), Some(21)));
+    }
+}
@@ -1,50 +1,132 @@
 use crate::{config, range_union};
 
+/// A language hyperpolyglot recognized but we have no parser for.
+#[derive(Debug)]
+pub struct UnsupportedLanguage(pub String);
+
+impl std::fmt::Display for UnsupportedLanguage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unsupported language: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnsupportedLanguage {}
+
+/// A grammar generated for a tree-sitter ABI version the linked tree-sitter can't load,
+/// as `Parser::set_language` reports it, plus which grammar and what to do about it.
+#[derive(Debug)]
+pub struct IncompatibleAbi {
+    pub language_name: config::LanguageName,
+    pub error: tree_sitter::LanguageError,
+}
+
+impl std::fmt::Display for IncompatibleAbi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the {:?} grammar can't be loaded: {}; pin a grammar release generated for a supported version",
+            self.language_name, self.error,
+        )
+    }
+}
+
+impl std::error::Error for IncompatibleAbi {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
 pub struct ParsedFile {
     pub language_name: config::LanguageName,
     pub source_code: std::vec::Vec<u8>,
     pub tree: tree_sitter::Tree,
 }
 
+pub const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Transcode `bytes` to UTF-8 if they start with a UTF-16 byte order mark.
+/// The mark itself comes through as a UTF-8 one, and unpaired surrogates become U+FFFD.
+pub fn decode_utf16(bytes: &[u8]) -> Option<std::vec::Vec<u8>> {
+    let from_bytes = match bytes {
+        [0xff, 0xfe, ..] => u16::from_le_bytes,
+        [0xfe, 0xff, ..] => u16::from_be_bytes,
+        _ => return None,
+    };
+    let chunks = bytes.chunks_exact(2);
+    let trailing_byte = !chunks.remainder().is_empty();
+    let mut decoded: String = char::decode_utf16(chunks.map(|c| from_bytes([c[0], c[1]])))
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    if trailing_byte {
+        decoded.push(char::REPLACEMENT_CHARACTER);
+    }
+    Some(decoded.into_bytes())
+}
+
+/// Which of our languages, if any, goes by `detected`, the name hyperpolyglot detected.
+pub fn language_name_for(detected: &str) -> Option<config::LanguageName> {
+    // strings from https://github.com/monkslc/hyperpolyglot/blob/master/languages.yml
+    Some(match detected {
+        "Rust" => config::LanguageName::Rust,
+        "Python" => config::LanguageName::Python,
+        "JavaScript" => config::LanguageName::Js,
+        "TypeScript" => config::LanguageName::Ts,
+        "TSX" => config::LanguageName::Tsx,
+        "C" => config::LanguageName::C,
+        "C++" => config::LanguageName::CPlusPlus,
+        "Go" => config::LanguageName::Go,
+        "C#" => config::LanguageName::CSharp,
+        "JSON" | "JSON with Comments" => config::LanguageName::Json,
+        "Shell" => config::LanguageName::Bash,
+        _ => return None,
+    })
+}
+
 impl ParsedFile {
-    pub fn from_filename(path: &std::ffi::OsString) -> Result<ParsedFile, std::io::Error> {
+    pub fn from_filename(
+        path: &std::ffi::OsString,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<ParsedFile, std::io::Error> {
         // TODO 0: add more languages
         // TODO 1: support embeds
         // TODO 2: group by language and do a second pass with language-specific regexes?
-        // strings from https://github.com/monkslc/hyperpolyglot/blob/master/languages.yml
-        let language_name = match hyperpolyglot::detect(std::path::Path::new(path))?
-            .ok_or_else(|| {
-                std::io::Error::new(std::io::ErrorKind::Unsupported, format!("{:?}", path))
-            })?
-            .language()
-        {
-            "Rust" => config::LanguageName::Rust,
-            "Python" => config::LanguageName::Python,
-            "JavaScript" => config::LanguageName::Js,
-            "TypeScript" => config::LanguageName::Ts,
-            "TSX" => config::LanguageName::Tsx,
-            "C" => config::LanguageName::C,
-            "C++" => config::LanguageName::CPlusPlus,
-            "Go" => config::LanguageName::Go,
-            other_language => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Unsupported,
-                    other_language,
-                ))
-            }
-        };
+        let detected = hyperpolyglot::detect(std::path::Path::new(path))?
+            .ok_or_else(|| unsupported(path, None))?
+            .language();
+        let language_name =
+            language_name_for(detected).ok_or_else(|| unsupported(path, Some(detected)))?;
         let source_code = std::fs::read(path)?;
-        Self::from_bytes(source_code, language_name)
+        Self::from_bytes(source_code, language_name, timeout)
     }
 
     pub fn from_bytes(
-        source_code: Vec<u8>,
+        mut source_code: Vec<u8>,
         language_name: config::LanguageName,
+        timeout: Option<std::time::Duration>,
     ) -> Result<ParsedFile, std::io::Error> {
+        // everything downstream assumes UTF-8, so transcode here and show output as UTF-8 too
+        if let Some(transcoded) = decode_utf16(&source_code) {
+            source_code = transcoded;
+        }
+        if source_code.starts_with(UTF8_BOM) {
+            source_code.drain(..UTF8_BOM.len());
+        }
         let mut parser = tree_sitter::Parser::new();
         parser
             .set_language(&language_name.get_language())
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            .map_err(|error| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    IncompatibleAbi {
+                        language_name,
+                        error,
+                    },
+                )
+            })?;
+        if let Some(t) = timeout {
+            // tree-sitter treats 0 as no timeout, so round up to keep tiny budgets meaningful
+            parser.set_timeout_micros((t.as_micros() as u64).max(1));
+        }
         let tree = parser
             .parse(&source_code, None)
             .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::TimedOut, ""))?;
@@ -54,69 +136,425 @@ impl ParsedFile {
             tree,
         })
     }
+
+    /// Every capture of `query` in this file, in order, like so:
+    /// `file_info.run_query(&compile_query(file_info.language_name, "(identifier) @id")?)`.
+    pub fn run_query(&self, query: &tree_sitter::Query) -> std::vec::Vec<Capture> {
+        let mut cursor = tree_sitter::QueryCursor::new();
+        cursor
+            .captures(query, self.tree.root_node(), self.source_code.as_slice())
+            .map(|(query_match, i)| {
+                let capture = query_match.captures[i];
+                Capture {
+                    name: String::from(query.capture_names()[capture.index as usize]),
+                    kind: capture.node.kind(),
+                    byte_range: capture.node.byte_range(),
+                    start: capture.node.start_position(),
+                    end: capture.node.end_position(),
+                    text: String::from_utf8_lossy(&self.source_code[capture.node.byte_range()])
+                        .into_owned(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Compile `query` for files in `language_name`, to pass to [`ParsedFile::run_query`].
+pub fn compile_query(
+    language_name: config::LanguageName,
+    query: &str,
+) -> Result<tree_sitter::Query, std::io::Error> {
+    tree_sitter::Query::new(&language_name.get_language(), query)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+}
+
+/// A node some query captured, and where it is in the (UTF-8, unmarked) source it came from.
+#[derive(Debug, PartialEq)]
+pub struct Capture {
+    /// without the @
+    pub name: String,
+    pub kind: &'static str,
+    pub byte_range: std::ops::Range<usize>,
+    pub start: tree_sitter::Point,
+    pub end: tree_sitter::Point,
+    pub text: String,
+}
+
+/// The error for a file at `path` in a language we can't parse, or that isn't detected at all.
+fn unsupported(path: &std::ffi::OsStr, detected: Option<&str>) -> std::io::Error {
+    match detected {
+        Some(detected) => std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            UnsupportedLanguage(String::from(detected)),
+        ),
+        None => std::io::Error::new(std::io::ErrorKind::Unsupported, format!("{:?}", path)),
+    }
 }
 
+/// Keeps parsed files around between recursion passes, so we only parse each file once per run
+/// unless it changes underneath us. Evicts the oldest entry once it holds `capacity` files.
+pub struct ParsedFileCache {
+    capacity: usize,
+    entries:
+        std::collections::HashMap<std::ffi::OsString, (Option<std::time::SystemTime>, ParsedFile)>,
+    insertion_order: std::collections::VecDeque<std::ffi::OsString>,
+    /// files we can't parse, and what language they were detected as if any, so each recursion
+    /// pass doesn't detect them all over again; they aren't expected to change mid-run
+    unsupported: std::collections::HashMap<std::ffi::OsString, Option<String>>,
+}
+
+impl ParsedFileCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Default::default(),
+            insertion_order: Default::default(),
+            unsupported: Default::default(),
+        }
+    }
+
+    pub fn get_or_parse(
+        &mut self,
+        path: &std::ffi::OsString,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<&ParsedFile, std::io::Error> {
+        if let Some(detected) = self.unsupported.get(path) {
+            return Err(unsupported(path, detected.as_deref()));
+        }
+        let mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let is_fresh = match self.entries.get(path) {
+            Some((cached_mtime, _)) => mtime.is_some() && *cached_mtime == mtime,
+            None => false,
+        };
+        if !is_fresh {
+            let parsed_file = match ParsedFile::from_filename(path, timeout) {
+                Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+                    let detected = e
+                        .get_ref()
+                        .and_then(|inner| inner.downcast_ref::<UnsupportedLanguage>())
+                        .map(|UnsupportedLanguage(language)| language.clone());
+                    self.unsupported.insert(path.clone(), detected);
+                    return Err(e);
+                }
+                result => result?,
+            };
+            if !self.entries.contains_key(path) {
+                if self.insertion_order.len() >= self.capacity {
+                    if let Some(oldest) = self.insertion_order.pop_front() {
+                        self.entries.remove(&oldest);
+                    }
+                }
+                self.insertion_order.push_back(path.clone());
+            }
+            self.entries.insert(path.clone(), (mtime, parsed_file));
+        }
+        Ok(&self.entries[path].1)
+    }
+}
+
+/// Names matching `pattern` (or not, with `invert`), sorted, each with where it's first defined.
+pub fn find_names(
+    source_code: &[u8],
+    tree: &tree_sitter::Tree,
+    language_info: &config::LanguageInfo,
+    pattern: &regex::Regex,
+    invert: bool,
+) -> std::vec::Vec<(String, tree_sitter::Point)> {
+    let mut names = find_name_sites(source_code, tree, language_info, pattern, invert);
+    names.dedup_by(|a, b| a.0 == b.0);
+    names
+}
+
+/// Like `find_names`, but with every place each name is defined, sorted by name then position.
+pub fn find_name_sites(
+    source_code: &[u8],
+    tree: &tree_sitter::Tree,
+    language_info: &config::LanguageInfo,
+    pattern: &regex::Regex,
+    invert: bool,
+) -> std::vec::Vec<(String, tree_sitter::Point)> {
+    let mut cursor = tree_sitter::QueryCursor::new();
+    let mut names: std::vec::Vec<(String, tree_sitter::Point)> = std::vec::Vec::new();
+    for node_query in language_info.match_patterns.iter() {
+        let name_idx = node_query.capture_index_for_name("name").unwrap();
+        for query_match in cursor.matches(node_query, tree.root_node(), source_code) {
+            for capture in query_match
+                .captures
+                .iter()
+                .filter(|capture| capture.index == name_idx)
+            {
+                let name = std::str::from_utf8(&source_code[capture.node.byte_range()]).unwrap();
+                if pattern.is_match(name) != invert {
+                    names.push((String::from(name), capture.node.start_position()));
+                }
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// One definition of a matching name, kept apart from any others in the same file.
+pub struct Definition {
+    pub name: String,
+    /// where the name itself starts, which may be after where the definition starts
+    pub position: tree_sitter::Point,
+    /// rows of the definition itself
+    pub range: std::ops::Range<usize>,
+    /// rows shown along with it: attached comments and such, then headers of enclosing scopes
+    pub context_ranges: std::vec::Vec<std::ops::Range<usize>>,
+    /// names of the enclosing scopes shown as context, innermost first
+    pub parent_names: std::vec::Vec<String>,
+    /// calls to look up when recursing from this definition alone
+    pub recurse_names: std::vec::Vec<RecurseName>,
+    /// text of the comments attached before this definition, top to bottom
+    pub docs: String,
+}
+
+impl Definition {
+    /// Lines to print for this definition alone, context included.
+    pub fn ranges(&self) -> range_union::RangeUnion {
+        let mut ranges: range_union::RangeUnion = Default::default();
+        ranges.push(&self.range);
+        ranges.extend(&self.context_ranges);
+        ranges
+    }
+}
+
+pub struct SearchResult {
+    pub ranges: range_union::RangeUnion,
+    /// sorted and deduplicated
+    pub recurse_names: std::vec::Vec<RecurseName>,
+    /// one entry per matching definition, in the order the queries found them
+    pub definitions: std::vec::Vec<Definition>,
+}
+
+impl From<std::vec::Vec<Definition>> for SearchResult {
+    fn from(definitions: std::vec::Vec<Definition>) -> Self {
+        let mut ranges: range_union::RangeUnion = Default::default();
+        let mut recurse_names: std::vec::Vec<RecurseName> = std::vec::Vec::new();
+        for definition in definitions.iter() {
+            for range in definition.ranges().iter() {
+                ranges.push(range);
+            }
+            recurse_names.extend(definition.recurse_names.iter().cloned());
+        }
+        recurse_names.sort();
+        recurse_names.dedup();
+        SearchResult {
+            ranges,
+            recurse_names,
+            definitions,
+        }
+    }
+}
+
+impl SearchResult {
+    /// Keep only definitions nested in a scope named `qualifier`, if there are any,
+    /// so `Thing.make()` recurses into `Thing`'s `make` rather than every `make` around.
+    pub fn prefer_qualifier(&mut self, qualifier: &str) {
+        if !self
+            .definitions
+            .iter()
+            .any(|d| d.parent_names.iter().any(|p| p == qualifier))
+        {
+            return;
+        }
+        self.retain(|d| d.parent_names.iter().any(|p| p == qualifier));
+    }
+
+    /// Leave out definitions inside any of the `printed` spans, keeping the rest whole, context and all.
+    pub fn drop_printed(&mut self, printed: &[std::ops::Range<usize>]) {
+        self.retain(|d| {
+            !printed
+                .iter()
+                .any(|span| span.start <= d.range.start && d.range.end <= span.end)
+        });
+    }
+
+    /// Keep only the definitions `keep` accepts, recomputing ranges and recurse names from them.
+    pub fn retain(&mut self, keep: impl FnMut(&Definition) -> bool) {
+        self.definitions.retain(keep);
+        *self = std::mem::take(&mut self.definitions).into();
+    }
+}
+
+/// A call to look up when recursing, and what it was called on if the recurse query captured
+/// a @qualifier, like `Thing` in `Thing.make()`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RecurseName {
+    pub name: String,
+    pub qualifier: Option<String>,
+}
+
+/// The one name all of `calls` are to, if there is one, since recursion follows one name per pass.
+/// Calls like `f()` and `a.f()` count as one; the qualifier stays only if every call agrees on it.
+pub fn recurse_target(calls: &[RecurseName]) -> Option<RecurseName> {
+    let first = calls.first()?;
+    if !calls.iter().all(|call| call.name == first.name) {
+        return None;
+    }
+    Some(RecurseName {
+        name: first.name.clone(),
+        qualifier: match calls.iter().all(|call| call.qualifier == first.qualifier) {
+            true => first.qualifier.clone(),
+            false => None,
+        },
+    })
+}
+
+/// Whether nothing but whitespace comes before `node` on the row it starts on.
+fn starts_line(node: tree_sitter::Node, source_code: &[u8]) -> bool {
+    let start = node.start_byte();
+    source_code[start - node.start_position().column..start]
+        .iter()
+        .all(u8::is_ascii_whitespace)
+}
+
+/// The row after `node` ends, or with `trim_trailing_blank_lines`, after its last non-blank row.
+/// Never the row it starts on, so even a zero-width node gets a line.
+fn end_row(node: tree_sitter::Node, source_code: &[u8], trim_trailing_blank_lines: bool) -> usize {
+    let end_row = node.range().end_point.row;
+    if !trim_trailing_blank_lines {
+        return end_row.saturating_add(1);
+    }
+    let text = &source_code[node.byte_range()];
+    let blank_rows = text[text.trim_ascii_end().len()..]
+        .iter()
+        .filter(|c| **c == b'\n')
+        .count();
+    end_row
+        .saturating_sub(blank_rows)
+        .max(node.range().start_point.row)
+        .saturating_add(1)
+}
+
+/// Lines to print for definitions of names matching `pattern` (or not, with `invert`), all merged.
 pub fn find_definition(
     source_code: &[u8],
     tree: &tree_sitter::Tree,
     language_info: &config::LanguageInfo,
     pattern: &regex::Regex,
+    invert: bool,
+    recurse: bool,
+    full_scope: bool,
+) -> SearchResult {
+    find_definitions_detailed(
+        source_code,
+        tree,
+        language_info,
+        pattern,
+        invert,
+        recurse,
+        full_scope,
+    )
+    .into()
+}
+
+/// Each definition of a name matching `pattern` (or not, with `invert`), with its own context.
+pub fn find_definitions_detailed(
+    source_code: &[u8],
+    tree: &tree_sitter::Tree,
+    language_info: &config::LanguageInfo,
+    pattern: &regex::Regex,
+    invert: bool,
     recurse: bool,
-) -> (range_union::RangeUnion, std::vec::Vec<String>) {
-    let mut result: range_union::RangeUnion = Default::default();
+    full_scope: bool,
+) -> std::vec::Vec<Definition> {
+    let mut definitions: std::vec::Vec<Definition> = std::vec::Vec::new();
     let mut cursor = tree_sitter::QueryCursor::new();
     let mut recurse_cursor = tree_sitter::QueryCursor::new();
-    let mut recurse_names: std::vec::Vec<String> = std::vec::Vec::new();
     //let mut context_cursor = tree_sitter::QueryCursor::new();
     //context_cursor.set_max_start_depth(0);
     for node_query in language_info.match_patterns.iter() {
         let name_idx = node_query.capture_index_for_name("name").unwrap();
         let def_idx = node_query.capture_index_for_name("def").unwrap();
-        for query_match in cursor
-            .matches(node_query, tree.root_node(), source_code)
-            .filter(|query_match| {
-                query_match.captures.iter().any(|capture| {
-                    capture.index == name_idx
-                        && pattern.is_match(
-                            std::str::from_utf8(&source_code[capture.node.byte_range()]).unwrap(),
-                        )
+        for query_match in cursor.matches(node_query, tree.root_node(), source_code) {
+            let Some((name, position)) = query_match
+                .captures
+                .iter()
+                .filter(|capture| capture.index == name_idx)
+                .map(|capture| {
+                    (
+                        std::str::from_utf8(&source_code[capture.node.byte_range()]).unwrap(),
+                        capture.node.start_position(),
+                    )
                 })
-            })
-        {
+                .find(|(name, _)| pattern.is_match(name) != invert)
+            else {
+                continue;
+            };
             for capture in query_match
                 .captures
                 .iter()
                 .filter(|capture| capture.index == def_idx)
             {
                 let mut node = capture.node;
-                result.push(
-                    node.range().start_point.row..node.range().end_point.row.saturating_add(1),
-                );
+                let mut definition = Definition {
+                    name: String::from(name),
+                    position,
+                    range: node.range().start_point.row
+                        ..end_row(node, source_code, language_info.trim_trailing_blank_lines),
+                    context_ranges: Default::default(),
+                    parent_names: Default::default(),
+                    recurse_names: Default::default(),
+                    docs: Default::default(),
+                };
                 // find names to look up for recursion
                 if recurse {
+                    // keyed by where each name is, so a qualified capture beats a bare one of the same call
+                    let mut calls: std::collections::BTreeMap<usize, RecurseName> =
+                        Default::default();
                     for recurse_query in language_info.recurse_patterns.iter() {
-                        let recurse_name_idx = node_query.capture_index_for_name("name").unwrap();
+                        let recurse_name_idx =
+                            recurse_query.capture_index_for_name("name").unwrap();
+                        let qualifier_idx = recurse_query.capture_index_for_name("qualifier");
                         for recurse_match in
                             recurse_cursor.matches(recurse_query, node, source_code)
                         {
-                            for recurse_capture in recurse_match
-                                .captures
-                                .iter()
-                                .filter(|recurse_capture| recurse_capture.index == recurse_name_idx)
-                            {
-                                let recurse_name = std::str::from_utf8(
-                                    &source_code[recurse_capture.node.byte_range()],
-                                )
-                                .unwrap();
-                                recurse_names.push(String::from(recurse_name));
+                            let find_capture = |index| {
+                                recurse_match
+                                    .captures
+                                    .iter()
+                                    .find(|recurse_capture| recurse_capture.index == index)
+                                    .map(|recurse_capture| recurse_capture.node)
+                            };
+                            let Some(name_node) = find_capture(recurse_name_idx) else {
+                                continue;
+                            };
+                            let recurse_name =
+                                std::str::from_utf8(&source_code[name_node.byte_range()]).unwrap();
+                            let call = RecurseName {
+                                name: String::from(recurse_name),
+                                qualifier: qualifier_idx.and_then(find_capture).map(
+                                    |qualifier_node| {
+                                        String::from(
+                                            std::str::from_utf8(
+                                                &source_code[qualifier_node.byte_range()],
+                                            )
+                                            .unwrap(),
+                                        )
+                                    },
+                                ),
+                            };
+                            match call.qualifier {
+                                Some(_) => {
+                                    calls.insert(name_node.start_byte(), call);
+                                }
+                                None => {
+                                    calls.entry(name_node.start_byte()).or_insert(call);
+                                }
                             }
                         }
                     }
+                    definition.recurse_names.extend(calls.into_values());
                 }
                 // include preceding neighbors as context while they remain relevant
                 // such as comments, python decorators, rust attributes, and c++ template arguments
                 let mut last_ambiguously_attached_sibling_range: Option<std::ops::Range<usize>> =
                     None;
+                let mut comments: std::vec::Vec<&str> = std::vec::Vec::new();
                 while let Some(sibling) = node.prev_sibling() {
                     if match std::num::NonZero::new(sibling.kind_id()) {
                         None => false,
@@ -125,14 +563,19 @@ pub fn find_definition(
                         let new_sibling_range = sibling.range().start_point.row
                             ..sibling.range().end_point.row.saturating_add(1);
                         if let Some(r) = last_ambiguously_attached_sibling_range {
-                            result.push(r);
+                            definition.context_ranges.push(r);
                         }
                         last_ambiguously_attached_sibling_range = Some(new_sibling_range);
+                        if sibling.kind().contains("comment") {
+                            comments.push(
+                                std::str::from_utf8(&source_code[sibling.byte_range()]).unwrap(),
+                            );
+                        }
                         node = sibling;
                     } else {
                         if let Some(r) = last_ambiguously_attached_sibling_range {
                             if sibling.range().end_point.row.saturating_add(1) < r.end {
-                                result.push(
+                                definition.context_ranges.push(
                                     sibling.range().end_point.row.saturating_add(1).max(r.start)
                                         ..r.end,
                                 );
@@ -143,8 +586,10 @@ pub fn find_definition(
                     }
                 }
                 if let Some(r) = last_ambiguously_attached_sibling_range {
-                    result.push(r);
+                    definition.context_ranges.push(r);
                 }
+                comments.reverse();
+                definition.docs = comments.join("\n");
                 // then include a header line from each relevant ancestor
                 while let Some(parent) = node.parent() {
                     // TODO interval arithmetic
@@ -153,53 +598,445 @@ pub fn find_definition(
                         Some(kind_id) => language_info.parent_patterns.contains(&kind_id),
                     } {
                         let context_start = parent.range().start_point.row;
-                        let context_end = context_start.max(
-                            language_info
-                                .parent_exclusions
-                                .iter()
-                                .filter_map(|field_id| parent.child_by_field_id((*field_id).get()))
-                                .map(|c| {
-                                    c.range().start_point.row.saturating_sub(1)
-                                    // TODO only subtract if exclusion is start of line?
-                                })
-                                .min()
-                                .unwrap_or(parent.range().end_point.row),
-                        );
-                        result.push(context_start..context_end.saturating_add(1));
+                        let context_end = if full_scope {
+                            parent.range().end_point.row
+                        } else {
+                            context_start.max(
+                                language_info
+                                    .parent_exclusions
+                                    .iter()
+                                    .filter_map(|field_id| {
+                                        parent.child_by_field_id((*field_id).get())
+                                    })
+                                    .map(|c| {
+                                        // keep a header line that the excluded part merely ends,
+                                        // like `where T: Copy {`
+                                        match starts_line(c, source_code) {
+                                            true => c.range().start_point.row.saturating_sub(1),
+                                            false => c.range().start_point.row,
+                                        }
+                                    })
+                                    .min()
+                                    .unwrap_or(parent.range().end_point.row),
+                            )
+                        };
+                        definition
+                            .context_ranges
+                            .push(context_start..context_end.saturating_add(1));
+                        if let Some(parent_name) = parent.child_by_field_name("name") {
+                            definition.parent_names.push(String::from(
+                                std::str::from_utf8(&source_code[parent_name.byte_range()])
+                                    .unwrap(),
+                            ));
+                        }
                     }
                     node = parent;
                 }
+                definitions.push(definition);
             }
         }
     }
-    recurse_names.sort();
-    recurse_names.dedup();
-    (result, recurse_names)
+    definitions
 }
 
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod tests {
     use super::*;
 
-    fn verify_examples(
+    type Case<'a> = (&'a str, Vec<std::ops::Range<usize>>, Vec<&'a str>);
+
+    fn fixture(
         language_name: config::LanguageName,
         source: &[u8],
-        cases: &[(&str, Vec<std::ops::Range<usize>>, Vec<&str>)],
-    ) {
+    ) -> (config::LanguageInfo, ParsedFile) {
         let config = config::Config::load_default();
         let language_info = config.get_language_info(language_name).unwrap().unwrap();
+        let file_info = ParsedFile::from_bytes(source.to_vec(), language_name, None).unwrap();
+        (language_info, file_info)
+    }
+
+    fn search(
+        language_info: &config::LanguageInfo,
+        file_info: &ParsedFile,
+        pattern: &str,
+        recurse: bool,
+        full_scope: bool,
+    ) -> SearchResult {
+        find_definition(
+            &file_info.source_code,
+            &file_info.tree,
+            language_info,
+            &regex::Regex::new(pattern).unwrap(),
+            false,
+            recurse,
+            full_scope,
+        )
+    }
+
+    fn verify_examples(language_name: config::LanguageName, source: &[u8], cases: &[Case]) {
+        let (language_info, file_info) = fixture(language_name, source);
+        for (query, expect_ranges, expect_recurses) in cases {
+            let pattern = String::from("^") + query + "$";
+            let result = search(&language_info, &file_info, &pattern, true, false);
+            let result_vec: Vec<_> = result.ranges.iter().collect();
+            assert_eq!(result_vec, *expect_ranges);
+            // calls to the same name count once, whatever they're called on
+            let mut recurse_names: Vec<&str> = result
+                .recurse_names
+                .iter()
+                .map(|r| r.name.as_str())
+                .collect();
+            recurse_names.dedup();
+            assert_eq!(recurse_names, *expect_recurses);
+        }
+    }
+
+    #[test]
+    fn linked_grammars_are_compatible() {
+        use strum::IntoEnumIterator;
+        for language_name in config::LanguageName::iter() {
+            let result = ParsedFile::from_bytes(std::vec::Vec::new(), language_name, None);
+            assert!(result.is_ok(), "{:?}", result.err());
+        }
+    }
+
+    #[test]
+    fn parse_timeout() {
+        let source = include_bytes!("../test_cases/python.py").repeat(1000);
+        let result = ParsedFile::from_bytes(
+            source,
+            config::LanguageName::Python,
+            Some(std::time::Duration::from_micros(1)),
+        );
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::TimedOut)
+        );
+    }
+
+    #[test]
+    fn parsed_file_cache_eviction() {
+        let mut cache = ParsedFileCache::new(1);
+        let python = std::ffi::OsString::from("test_cases/python.py");
+        let c = std::ffi::OsString::from("test_cases/c.c");
+        let language_name = cache.get_or_parse(&python, None).unwrap().language_name;
+        assert_eq!(language_name, config::LanguageName::Python);
+        let language_name = cache.get_or_parse(&c, None).unwrap().language_name;
+        assert_eq!(language_name, config::LanguageName::C);
+        assert_eq!(cache.entries.len(), 1);
+        assert!(cache.entries.contains_key(&c));
+    }
+
+    #[test]
+    fn parsed_file_cache_unsupported() {
+        let mut cache = ParsedFileCache::new(1);
+        let readme = std::ffi::OsString::from("README.md");
+        for _ in 0..2 {
+            let e = cache.get_or_parse(&readme, None).err().unwrap();
+            let inner = e.get_ref().unwrap().downcast_ref::<UnsupportedLanguage>();
+            assert_eq!(inner.unwrap().0, "Markdown");
+        }
+        assert_eq!(cache.unsupported[&readme].as_deref(), Some("Markdown"));
+    }
+
+    #[test]
+    fn run_query() {
+        let file_info = ParsedFile::from_bytes(
+            b"\xef\xbb\xbfdef f(x):\n    return g(x)\n".to_vec(),
+            config::LanguageName::Python,
+            None,
+        )
+        .unwrap();
+        let query = compile_query(
+            file_info.language_name,
+            "(call function: (identifier) @callee arguments: (_) @args)",
+        )
+        .unwrap();
+        let captures = file_info.run_query(&query);
+        assert_eq!(
+            captures,
+            vec![
+                Capture {
+                    name: String::from("callee"),
+                    kind: "identifier",
+                    byte_range: 21..22,
+                    start: tree_sitter::Point::new(1, 11),
+                    end: tree_sitter::Point::new(1, 12),
+                    text: String::from("g"),
+                },
+                Capture {
+                    name: String::from("args"),
+                    kind: "argument_list",
+                    byte_range: 22..25,
+                    start: tree_sitter::Point::new(1, 12),
+                    end: tree_sitter::Point::new(1, 15),
+                    text: String::from("(x)"),
+                },
+            ]
+        );
+        let e = compile_query(file_info.language_name, "(call")
+            .err()
+            .unwrap();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn python_names() {
+        let (language_info, file_info) = fixture(
+            config::LanguageName::Python,
+            include_bytes!("../test_cases/python.py"),
+        );
+        let pattern = regex::Regex::new("^(.*ations|t.*)$").unwrap();
+        let names = find_names(
+            &file_info.source_code,
+            &file_info.tree,
+            &language_info,
+            &pattern,
+            false,
+        );
+        assert_eq!(
+            names.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            vec!["combinations", "permutations", "ten", "three", "two"]
+        );
+        assert_eq!(names[0].1, tree_sitter::Point::new(65, 4));
+        let attr = regex::Regex::new("^attr$").unwrap();
+        let sites = find_name_sites(
+            &file_info.source_code,
+            &file_info.tree,
+            &language_info,
+            &attr,
+            false,
+        );
+        assert!(sites.len() > 1);
+        assert!(sites.iter().all(|(name, _)| name == "attr"));
+        assert!(sites.windows(2).all(|w| w[0].1 < w[1].1));
+        let inverted = find_names(
+            &file_info.source_code,
+            &file_info.tree,
+            &language_info,
+            &pattern,
+            true,
+        );
+        assert!(!inverted.is_empty());
+        assert!(inverted.iter().all(|(name, _)| !pattern.is_match(name)));
+    }
+
+    #[test]
+    fn python_ranges_per_name() {
+        let (language_info, file_info) = fixture(
+            config::LanguageName::Python,
+            include_bytes!("../test_cases/python.py"),
+        );
+        let result = search(
+            &language_info,
+            &file_info,
+            "^(combinations|permutations)$",
+            false,
+            false,
+        );
+        let per_name: Vec<(&str, Vec<std::ops::Range<usize>>)> = result
+            .definitions
+            .iter()
+            .map(|m| (m.name.as_str(), m.ranges().iter().collect()))
+            .collect();
+        assert_eq!(
+            per_name,
+            vec![
+                ("permutations", vec![59..63]),
+                ("combinations", vec![65..67])
+            ]
+        );
+        assert_eq!(
+            result.ranges.iter().collect::<Vec<_>>(),
+            vec![59..63, 65..67]
+        );
+    }
+
+    #[test]
+    fn trailing_blank_lines() {
+        let (mut language_info, file_info) =
+            fixture(config::LanguageName::C, include_bytes!("../test_cases/c.c"));
+        let mut ranges = |trim_trailing_blank_lines| {
+            language_info.trim_trailing_blank_lines = trim_trailing_blank_lines;
+            search(&language_info, &file_info, "^ONE$", false, false)
+                .ranges
+                .iter()
+                .collect::<Vec<_>>()
+        };
+        // the #define node runs through the blank line after it
+        assert_eq!(ranges(false), vec![2..4]);
+        assert_eq!(ranges(true), vec![2..3]);
+    }
+
+    #[test]
+    fn python_qualified_recursion() {
+        let (language_info, file_info) = fixture(
+            config::LanguageName::Python,
+            b"class Thing:\n    def make():\n        pass\n\nclass Other:\n    def make():\n        pass\n\ndef build():\n    return Thing.make()\n",
+        );
+        let result = search(&language_info, &file_info, "^build$", true, false);
+        assert_eq!(
+            result.recurse_names,
+            vec![RecurseName {
+                name: String::from("make"),
+                qualifier: Some(String::from("Thing")),
+            }]
+        );
+        let mut result = search(&language_info, &file_info, "^make$", true, false);
+        assert_eq!(result.ranges.iter().collect::<Vec<_>>(), vec![0..3, 4..7]);
+        result.prefer_qualifier("Thing");
+        assert_eq!(result.ranges.iter().collect::<Vec<_>>(), vec![0..3]);
+        // with no definitions in a matching scope, keep them all
+        let mut result = search(&language_info, &file_info, "^make$", true, false);
+        result.prefer_qualifier("self");
+        assert_eq!(result.ranges.iter().collect::<Vec<_>>(), vec![0..3, 4..7]);
+        // one name called two ways is still one name to recurse into
+        let call = |qualifier: Option<&str>| RecurseName {
+            name: String::from("make"),
+            qualifier: qualifier.map(String::from),
+        };
+        assert_eq!(
+            recurse_target(&[call(None), call(Some("Thing"))]),
+            Some(call(None))
+        );
+        assert_eq!(
+            recurse_target(&[call(Some("Thing")), call(Some("Thing"))]),
+            Some(call(Some("Thing")))
+        );
+        assert_eq!(
+            recurse_target(&[
+                call(None),
+                RecurseName {
+                    name: String::from("build"),
+                    qualifier: None
+                }
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn recursion_keeps_shared_headers() {
+        let (language_info, file_info) = fixture(
+            config::LanguageName::Python,
+            b"class C:\n    def a(self):\n        return helper()\n\n    def helper(self):\n        return 1\n",
+        );
+        let printed: Vec<_> = search(&language_info, &file_info, "^a$", true, false)
+            .definitions
+            .into_iter()
+            .map(|d| d.range)
+            .collect();
+        // a second pass keeps the header it shares with a definition the first pass printed
+        let mut result = search(&language_info, &file_info, "^(a|helper)$", true, false);
+        result.drop_printed(&printed);
+        assert_eq!(result.ranges.iter().collect::<Vec<_>>(), vec![0..1, 4..6]);
+    }
+
+    #[test]
+    fn python_bom() {
+        let source = include_bytes!("../test_cases/python.py");
+        let ranges = |source_code: &[u8]| {
+            let (language_info, file_info) = fixture(config::LanguageName::Python, source_code);
+            let result = search(&language_info, &file_info, "^combinations$", false, false);
+            (
+                file_info.source_code.starts_with(UTF8_BOM),
+                result.ranges.iter().collect::<Vec<_>>(),
+            )
+        };
+        let (kept_bom, with_bom) = ranges(&[UTF8_BOM, source].concat());
+        let (_, without_bom) = ranges(source);
+        assert!(!kept_bom);
+        assert!(!without_bom.is_empty());
+        assert_eq!(with_bom, without_bom);
+    }
+
+    #[test]
+    fn utf16_transcoding() {
+        assert_eq!(decode_utf16(b"def f(): pass"), None);
+        assert_eq!(
+            decode_utf16(b"\xff\xfed\x00\x3d\xd8\x00\xde\n\x00").unwrap(),
+            "\u{feff}d\u{1f600}\n".as_bytes(),
+        );
+        assert_eq!(
+            decode_utf16(b"\xfe\xff\x00d\xd8\x3d\x00").unwrap(),
+            "\u{feff}d\u{fffd}\u{fffd}".as_bytes(),
+        );
+        let source: std::vec::Vec<u8> = "\u{feff}def ten():\n    return 10\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let file_info = ParsedFile::from_bytes(source, config::LanguageName::Python, None).unwrap();
+        assert_eq!(file_info.source_code, b"def ten():\n    return 10\n");
+        assert!(!file_info.tree.root_node().has_error());
+    }
+
+    #[test]
+    fn zero_width_definitions() {
+        // a def with no body yet parses with an empty block
+        let source = b"x = 1\ndef f():\n";
+        let language_name = config::LanguageName::Python;
         let mut parser = tree_sitter::Parser::new();
         parser.set_language(&language_name.get_language()).unwrap();
         let tree = parser.parse(source, None).unwrap();
-        for (query, expect_ranges, expect_recurses) in cases {
-            let pattern = regex::Regex::new(&(String::from("^") + query + "$")).unwrap();
-            let (result, recurses) = find_definition(source, &tree, &language_info, &pattern, true);
-            let result_vec: Vec<_> = result.iter().collect();
-            assert_eq!(result_vec, *expect_ranges);
-            assert_eq!(recurses, *expect_recurses);
+        let pattern = regex::Regex::new("^f$").unwrap();
+        for trim_trailing_blank_lines in [false, true] {
+            let mut language_info = config::LanguageInfo::new(
+                &language_name.get_language(),
+                ["(function_definition name: (identifier) @name body: (block) @def)"],
+                [""; 0],
+                [""; 0],
+                [""; 0],
+                [""; 0],
+            )
+            .unwrap();
+            language_info.trim_trailing_blank_lines = trim_trailing_blank_lines;
+            let result =
+                find_definition(source, &tree, &language_info, &pattern, false, false, false);
+            assert_eq!(result.ranges.iter().collect::<Vec<_>>(), vec![1..2]);
         }
     }
 
+    #[test]
+    fn python_docs() {
+        let (language_info, file_info) = fixture(
+            config::LanguageName::Python,
+            include_bytes!("../test_cases/python.py"),
+        );
+        let mut result = search(&language_info, &file_info, "^(seven|eight)$", false, false);
+        let docs: Vec<_> = result.definitions.iter().map(|m| m.docs.as_str()).collect();
+        assert_eq!(
+            docs,
+            vec!["# this is a comment\n# with multiple lines\n# whee", ""]
+        );
+        result.retain(|m| m.docs.contains("whee"));
+        assert_eq!(result.ranges.iter().collect::<Vec<_>>(), vec![40..47]);
+        let definitions = find_definitions_detailed(
+            &file_info.source_code,
+            &file_info.tree,
+            &language_info,
+            &regex::Regex::new("^(seven|eight)$").unwrap(),
+            false,
+            false,
+            false,
+        );
+        let seven = &definitions[0];
+        assert_eq!(
+            (seven.range.clone(), seven.context_ranges.clone()),
+            (43..47, vec![42..43, 41..42, 40..41])
+        );
+    }
+
+    #[test]
+    fn python_full_scope() {
+        let (language_info, file_info) = fixture(
+            config::LanguageName::Python,
+            include_bytes!("../test_cases/python.py"),
+        );
+        let result = search(&language_info, &file_info, "^five$", false, true);
+        assert_eq!(result.ranges.iter().collect::<Vec<_>>(), vec![13..34]);
+    }
+
     #[test]
     fn python_examples() {
         // these ranges are 0-indexed and bat line numbers are 1-indexed so generate them with `nl -ba -v0`
@@ -226,7 +1063,9 @@ mod tests {
             ("permutations", vec![59..63], vec!["permutations"]),
             ("combinations", vec![65..67], vec!["factorial", "permutations"]),
             ("combinations2", vec![69..71], vec!["factorial"]),
-            ("attr", vec![73..78], vec!["setattr"]),
+            ("attr", vec![73..78], vec!["__setattr__", "__setitem__", "setattr"]),
+            ("fold", vec![82..83], vec!["reduce"]),  // aliased import, which recurses to the original name
+            ("product", vec![85..87], vec!["fold"]),
         ];
         verify_examples(
             config::LanguageName::Python,
@@ -271,6 +1110,9 @@ mod tests {
             ("eight", vec![0..1], vec![]),  // function argument
             ("nine", vec![0..1], vec![]),  // function argument with default
             ("ten", vec![0..1], vec![]),  // rest parameters
+            ("pad", vec![2..8], vec![]),  // overload signatures before the implementation
+            ("render", vec![9..16], vec![]),  // method with decorators and a comment
+            ("resize", vec![9..10, 17..20], vec![]),  // overloaded method
         ];
         verify_examples(
             config::LanguageName::Tsx,
@@ -284,7 +1126,7 @@ mod tests {
         // these ranges are 0-indexed and bat line numbers are 1-indexed so generate them with `nl -ba -v0`
         #[rustfmt::skip]
         let cases = [
-            ("ONE", vec![2..4], vec![]),  // #define, which I guess includes the line ending.
+            ("ONE", vec![2..3], vec![]),  // #define, which includes the line ending and a blank line we trim
             ("two", vec![5..6], vec![]),  // static const
             ("ThreeStruct", vec![7..11], vec![]),  // struct
             ("Three", vec![7..11], vec![]),  // typedef struct; see https://stackoverflow.com/a/1675446
@@ -294,7 +1136,7 @@ mod tests {
             ("four", vec![7..9], vec![]),  // member
             ("five", vec![7..8, 9..10], vec![]),  // array
             ("six", vec![21..22], vec![]),  // unreasonable levels of pointer nesting
-            ("SEVEN", vec![23..25, 33..35], vec![]),  // macro
+            ("SEVEN", vec![23..24, 33..34], vec![]),  // macro
             ("second_order", vec![25..32], vec![]),  // function definition
             ("callback", vec![25..30], vec![]),  // function pointer
             ("right", vec![25..30], vec![]),  // other function parameter
@@ -305,4 +1147,79 @@ mod tests {
             &cases,
         );
     }
+
+    #[test]
+    fn csharp_examples() {
+        // these ranges are 0-indexed and bat line numbers are 1-indexed so generate them with `nl -ba -v0`
+        #[rustfmt::skip]
+        let cases = [
+            ("Small", vec![3..4, 5..11], vec![]),  // enum, with doc comment
+            ("One", vec![3..4, 6..7, 8..9], vec![]),  // enum member
+            ("Counter", vec![3..4, 12..30, 31..38], vec!["Step"]),  // partial classes, and a constructor
+            ("count", vec![3..4, 12..14, 15..16], vec![]),  // field
+            ("Count", vec![3..4, 12..14, 17..18], vec![]),  // property
+            ("Increment", vec![3..4, 12..14, 24..29], vec!["Step"]),  // method, with comment
+            ("Point", vec![3..4, 39..40], vec![]),  // record
+            ("Width", vec![3..4, 41..42, 43..44], vec![]),  // struct field
+            ("Area", vec![3..4, 46..47, 48..49], vec![]),  // interface method
+            ("Examples", vec![3..51], vec!["Step"]),  // namespace
+        ];
+        verify_examples(
+            config::LanguageName::CSharp,
+            include_bytes!("../test_cases/csharp.cs"),
+            &cases,
+        );
+    }
+
+    #[test]
+    fn json_examples() {
+        // these ranges are 0-indexed and bat line numbers are 1-indexed so generate them with `nl -ba -v0`
+        #[rustfmt::skip]
+        let cases = [
+            ("compilerOptions", vec![1..8], vec![]),  // with comment
+            ("target", vec![2..4, 11..12], vec![]),  // nested and top-level
+            ("paths", vec![2..3, 4..7], vec![]),  // parent key as context
+            ("@app/\\*", vec![2..3, 4..6], vec![]),  // keys that aren't identifiers
+            ("include", vec![8..11], vec![]),  // array value
+        ];
+        verify_examples(
+            config::LanguageName::Json,
+            include_bytes!("../test_cases/json.json"),
+            &cases,
+        );
+    }
+
+    #[test]
+    fn rust_examples() {
+        // these ranges are 0-indexed and bat line numbers are 1-indexed so generate them with `nl -ba -v0`
+        #[rustfmt::skip]
+        let cases = [
+            ("area", vec![1..2, 8..11, 12..16], vec![]),  // where clause with the brace on its own line
+            ("fmt", vec![18..24], vec![]),  // where clause ending with the brace
+        ];
+        verify_examples(
+            config::LanguageName::Rust,
+            include_bytes!("../test_cases/rust.rs"),
+            &cases,
+        );
+    }
+
+    #[test]
+    fn bash_examples() {
+        // these ranges are 0-indexed and bat line numbers are 1-indexed so generate them with `nl -ba -v0`
+        #[rustfmt::skip]
+        let cases = [
+            ("GREETING", vec![0..2], vec![]),  // global, with the shebang as a preceding comment
+            ("greet", vec![3..7], vec!["echo"]),  // function, with comment
+            ("shout", vec![8..13], vec!["greet"]),  // function inside an if
+            ("loud", vec![8..11], vec![]),  // local inside a function inside an if
+            ("LAST", vec![15..17], vec![]),  // export inside a for loop
+            ("run", vec![19..22], vec!["greet"]),  // function inside a case
+        ];
+        verify_examples(
+            config::LanguageName::Bash,
+            include_bytes!("../test_cases/shell.sh"),
+            &cases,
+        );
+    }
 }
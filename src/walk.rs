@@ -0,0 +1,137 @@
+use crate::searches;
+
+/// List files under `root` whose contents match `pattern`, sorted, for when ripgrep isn't around.
+/// Like ripgrep, skips hidden files and, unless `text` is set, files that look binary (that is,
+/// contain a NUL byte, UTF-16 aside). With the `gitignore` feature also skips whatever `.gitignore` and friends say to.
+/// Whatever can't be read gets a warning and is left out, rather than failing the whole search.
+pub fn files_matching(
+    root: &std::path::Path,
+    pattern: &regex::Regex,
+    text: bool,
+) -> std::io::Result<std::vec::Vec<std::ffi::OsString>> {
+    let pattern = regex::bytes::Regex::new(pattern.as_str())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let mut filenames: std::vec::Vec<std::ffi::OsString> = list_files(root)
+        .into_iter()
+        .filter(|path| match std::fs::read(path) {
+            Ok(contents) => {
+                // UTF-16 is full of NULs, but searchable once transcoded the way parsing does
+                let contents = searches::decode_utf16(&contents).unwrap_or(contents);
+                (text || !contents.contains(&0)) && pattern.is_match(&contents)
+            }
+            Err(e) => {
+                log::warn!("{}: {}", path.to_string_lossy(), e);
+                false
+            }
+        })
+        .map(std::path::PathBuf::into_os_string)
+        .collect();
+    filenames.sort_unstable();
+    Ok(filenames)
+}
+
+#[cfg(feature = "gitignore")]
+fn list_files(root: &std::path::Path) -> std::vec::Vec<std::path::PathBuf> {
+    let mut files = std::vec::Vec::new();
+    for entry in ignore::WalkBuilder::new(root).build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("{}", e);
+                continue;
+            }
+        };
+        if entry.file_type().is_some_and(|t| t.is_file()) {
+            files.push(entry.into_path());
+        }
+    }
+    files
+}
+
+#[cfg(not(feature = "gitignore"))]
+fn list_files(root: &std::path::Path) -> std::vec::Vec<std::path::PathBuf> {
+    let mut files = std::vec::Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("{}: {}", dir.to_string_lossy(), e);
+                continue;
+            }
+        };
+        for entry in entries {
+            let (entry, file_type) = match entry.and_then(|e| e.file_type().map(|t| (e, t))) {
+                Ok(pair) => pair,
+                Err(e) => {
+                    log::warn!("{}: {}", dir.to_string_lossy(), e);
+                    continue;
+                }
+            };
+            if entry.file_name().as_encoded_bytes().starts_with(b".") {
+                continue;
+            }
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_finds_matching_files() {
+        let pattern = regex::Regex::new("def combinations").unwrap();
+        let filenames =
+            files_matching(std::path::Path::new("test_cases"), &pattern, false).unwrap();
+        assert_eq!(
+            filenames,
+            vec![std::ffi::OsString::from("test_cases/python.py")]
+        );
+    }
+
+    #[test]
+    fn walk_skips_binary_files_unless_text() {
+        let root = std::env::temp_dir().join(format!("dook-walk-test-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("binary.py"), b"def f():\0 pass\n").unwrap();
+        std::fs::write(root.join("text.py"), b"def f(): pass\n").unwrap();
+        let pattern = regex::Regex::new("def f").unwrap();
+        let found = [false, true].map(|text| {
+            files_matching(&root, &pattern, text)
+                .unwrap()
+                .into_iter()
+                .map(|path| {
+                    std::path::PathBuf::from(path)
+                        .file_name()
+                        .unwrap()
+                        .to_owned()
+                })
+                .collect::<std::vec::Vec<_>>()
+        });
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(found[0], ["text.py"]);
+        assert_eq!(found[1], ["binary.py", "text.py"]);
+    }
+
+    #[test]
+    fn walk_finds_utf16_files() {
+        let root = std::env::temp_dir().join(format!("dook-walk-utf16-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let source: std::vec::Vec<u8> = "\u{feff}def f(): pass\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        std::fs::write(root.join("utf16.py"), source).unwrap();
+        let pattern = regex::Regex::new("def f").unwrap();
+        let found = files_matching(&root, &pattern, false).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(found, [root.join("utf16.py").into_os_string()]);
+    }
+}
@@ -0,0 +1,51 @@
+using System;
+using System.Collections.Generic;
+
+namespace Dook.Examples
+{
+    /// <summary>The number one.</summary>
+    public enum Small
+    {
+        One = 1,
+        Two,
+    }
+
+    [Serializable]
+    public partial class Counter
+    {
+        private int count = 0;
+
+        public int Count { get; set; }
+
+        public Counter(int start)
+        {
+            count = start;
+        }
+
+        // adds one
+        public void Increment()
+        {
+            count += Step();
+        }
+    }
+
+    public partial class Counter
+    {
+        private int Step()
+        {
+            return 1;
+        }
+    }
+
+    public record Point(int X, int Y);
+
+    public struct Size
+    {
+        public int Width;
+    }
+
+    public interface IShape
+    {
+        double Area();
+    }
+}
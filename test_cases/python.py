@@ -78,3 +78,10 @@ def shenanigans(x):
     dict.__setitem__(x, 'attr', 4)  # yes 🦆
     setattr('attr', 'nope', 5)  # no!!1 🪿
     object.__setattr__(x, 'attr')  # I mean this would throw if you actually ran it
+
+
+from functools import reduce as fold
+
+
+def product(xs):
+    return fold(lambda p, q: p * q, xs)
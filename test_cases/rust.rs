@@ -0,0 +1,25 @@
+pub trait Shape {
+    fn area(&self) -> f64;
+}
+
+pub struct Square<T> {
+    side: T,
+}
+
+impl<T> Shape for Square<T>
+where
+    T: Copy + Into<f64>,
+{
+    fn area(&self) -> f64 {
+        let side: f64 = self.side.into();
+        side * side
+    }
+}
+
+impl<T> std::fmt::Display for Square<T>
+where
+    T: std::fmt::Display {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "square of side {}", self.side)
+    }
+}
@@ -0,0 +1,24 @@
+#!/bin/bash
+GREETING="hello"
+
+# says hello
+greet() {
+	echo "$GREETING $1"
+}
+
+if [ -n "$ZSH_VERSION" ]; then
+	shout() {
+		local loud=1
+		greet "$@"
+	}
+fi
+
+for name in one two; do
+	export LAST="$name"
+done
+
+case "$1" in
+	start)
+		run() { greet "$1"; }
+		;;
+esac
@@ -1 +1,21 @@
 const arr = (eight: number, nine: number = 2, ...ten: number) => null;
+
+// pads on the left
+function pad(s: string): string;
+function pad(n: number): string;
+function pad(x: string | number): string {
+    return String(x).padStart(8);
+}
+
+class Widget {
+    // draws itself
+    @memoize
+    @trace("render")
+    render(): string {
+        return pad(this.size);
+    }
+
+    resize(width: number): void;
+    resize(width: number, height: number): void;
+    resize(width: number, height?: number) {}
+}